@@ -0,0 +1,339 @@
+use std::{fs, path::Path};
+
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use de::Unexpected;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::{
+    input::read_records,
+    student::Students,
+    subject::{Division, Subjects},
+};
+
+const PATH: &str = "./data/csv/semester.csv";
+/// Sheet to read when `PATH` is an `.xlsx` workbook.
+const SHEET: &str = "학사일정";
+const PATH_OUTPUT: &str = "./calendars";
+
+const DAY: i32 = 24 * 60;
+const TIMEZONE: &str = "Asia/Seoul";
+
+/// First and last day of classes and the days off in between.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Semester {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub holidays: Vec<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    #[serde(rename = "구분")]
+    kind: String,
+    #[serde(rename = "날짜", deserialize_with = "date_from_string")]
+    date: NaiveDate,
+}
+
+fn date_from_string<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+        .map_err(|_| de::Error::invalid_value(Unexpected::Str(&s), &"YYYY-MM-DD"))
+}
+
+impl Semester {
+    /// Reads rows of `개강`, `종강` and `휴일` dates. Returns `None` if there is no such file.
+    pub fn read_csv() -> Option<Semester> {
+        if !Path::new(PATH).exists() {
+            return None;
+        }
+
+        let (mut start, mut end, mut holidays) = (None, None, Vec::new());
+        let (header, records) = read_records(PATH, SHEET, None);
+        for record in records {
+            let row: Row = record.deserialize(Some(&header)).unwrap();
+            match row.kind.trim() {
+                "개강" => start = Some(row.date),
+                "종강" => end = Some(row.date),
+                "휴일" => holidays.push(row.date),
+                other => panic!("unknown semester row {:?}", other),
+            }
+        }
+
+        Some(Semester {
+            start: start.expect("개강 date is missing"),
+            end: end.expect("종강 date is missing"),
+            holidays,
+        })
+    }
+
+    /// The first date on or after `start` falling on `day` (0 = 월).
+    fn first(&self, day: u32) -> NaiveDate {
+        let offset = (7 + day - self.start.weekday().num_days_from_monday()) % 7;
+        self.start + Duration::days(offset as i64)
+    }
+
+    /// Week of the semester `date` falls in, counting from 1 for the week (from 월) of `start`.
+    fn week(&self, date: NaiveDate) -> u32 {
+        let monday =
+            self.start - Duration::days(self.start.weekday().num_days_from_monday() as i64);
+        ((date - monday).num_days() / 7) as u32 + 1
+    }
+}
+
+/// A calendar with one weekly recurring event per lecture interval.
+pub fn to_ics(name: &str, divisions: &[&Division], semester: &Semester) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//saenaegi//freshman schedule//KO".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+        "BEGIN:VTIMEZONE".to_owned(),
+        format!("TZID:{}", TIMEZONE),
+        "BEGIN:STANDARD".to_owned(),
+        "DTSTART:19700101T000000".to_owned(),
+        "TZOFFSETFROM:+0900".to_owned(),
+        "TZOFFSETTO:+0900".to_owned(),
+        "TZNAME:KST".to_owned(),
+        "END:STANDARD".to_owned(),
+        "END:VTIMEZONE".to_owned(),
+    ];
+
+    for division in divisions {
+        for (i, h) in division.hour.iter().enumerate() {
+            // meetings in the weeks and dates the division meets in; the rest are excluded
+            let (from, until) = match division.dates {
+                Some([first, last]) => (first, last.min(semester.end)),
+                None => (semester.start, semester.end),
+            };
+            let (dates, skipped): (Vec<NaiveDate>, Vec<NaiveDate>) =
+                std::iter::successors(Some(semester.first((h[0] / DAY) as u32)), |d| {
+                    Some(*d + Duration::days(7))
+                })
+                .take_while(|d| *d <= until)
+                .partition(|d| *d >= from && division.weeks.contains(semester.week(*d)));
+            let first = match dates.first() {
+                Some(&first) => first,
+                None => continue,
+            };
+            let time = |minute: i32| {
+                let minute = minute % DAY;
+                format!("T{:02}{:02}00", minute / 60, minute % 60)
+            };
+            // `date` is the day of the start; an end at 24:00 falls on the next one
+            let at = |date: NaiveDate, minute: i32| {
+                let date = date + Duration::days((minute / DAY - h[0] / DAY) as i64);
+                format!("{}{}", date.format("%Y%m%d"), time(minute))
+            };
+
+            lines.push("BEGIN:VEVENT".to_owned());
+            lines.push(format!(
+                "UID:{}-{}-{}@saenaegi",
+                division.id, division.division, i
+            ));
+            lines.push(format!("DTSTAMP:{}", stamp));
+            lines.push(format!(
+                "SUMMARY:{}",
+                escape(&format!(
+                    "{} {} ({})",
+                    division.name, division.division, division.id
+                ))
+            ));
+            lines.push(format!("DTSTART;TZID={}:{}", TIMEZONE, at(first, h[0])));
+            lines.push(format!("DTEND;TZID={}:{}", TIMEZONE, at(first, h[1])));
+            // UNTIL must be in UTC when DTSTART has a TZID; 23:59:59 KST
+            lines.push(format!(
+                "RRULE:FREQ=WEEKLY;UNTIL={}T145959Z",
+                until.format("%Y%m%d")
+            ));
+            let mut excluded: Vec<NaiveDate> = skipped
+                .into_iter()
+                .filter(|d| *d > first)
+                .chain(
+                    semester
+                        .holidays
+                        .iter()
+                        .copied()
+                        .filter(|d| dates.contains(d)),
+                )
+                .collect();
+            excluded.sort();
+            excluded.dedup();
+            for date in excluded {
+                lines.push(format!("EXDATE;TZID={}:{}", TIMEZONE, at(date, h[0])));
+            }
+            lines.push("END:VEVENT".to_owned());
+        }
+    }
+    lines.push("END:VCALENDAR".to_owned());
+
+    lines.iter().map(|l| fold(l)).collect()
+}
+
+/// Escapes a TEXT value (RFC 5545 3.3.11).
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line at 75 octets without splitting a character, and terminates it with CRLF.
+fn fold(line: &str) -> String {
+    let mut buf = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            buf.push_str("\r\n ");
+            len = 1;
+        }
+        buf.push(c);
+        len += c.len_utf8();
+    }
+    buf.push_str("\r\n");
+    buf
+}
+
+/// Writes a calendar for every student and every division.
+pub fn write_calendars(subjects: &Subjects, students: &Students, semester: &Semester) {
+    fs::create_dir_all(format!("{}/students", PATH_OUTPUT)).unwrap();
+    fs::create_dir_all(format!("{}/divisions", PATH_OUTPUT)).unwrap();
+
+    for student in students.values() {
+        fs::write(
+            format!("{}/students/{}.ics", PATH_OUTPUT, student.id),
+            to_ics(
+                &format!("{} {}", student.id, student.name),
+                &student.subjects(subjects),
+                semester,
+            ),
+        )
+        .unwrap();
+    }
+
+    for (_, division) in subjects.iter() {
+        let (subject_id, division_id) = (division.subject, &division.division);
+        fs::write(
+            format!(
+                "{}/divisions/{}-{}.ics",
+                PATH_OUTPUT, subject_id, division_id
+            ),
+            to_ics(
+                &format!("{} {}", subject_id, division_id),
+                &[division],
+                semester,
+            ),
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::subject::Weeks;
+
+    #[test]
+    fn first_simple() {
+        // 2021-03-02 is a Tuesday
+        let semester = Semester {
+            start: NaiveDate::from_ymd_opt(2021, 3, 2).unwrap(),
+            end: NaiveDate::from_ymd_opt(2021, 6, 18).unwrap(),
+            holidays: Vec::new(),
+        };
+        assert_eq!(
+            semester.first(0),
+            NaiveDate::from_ymd_opt(2021, 3, 8).unwrap()
+        );
+        assert_eq!(
+            semester.first(1),
+            NaiveDate::from_ymd_opt(2021, 3, 2).unwrap()
+        );
+        assert_eq!(
+            semester.first(4),
+            NaiveDate::from_ymd_opt(2021, 3, 5).unwrap()
+        );
+    }
+
+    #[test]
+    fn ics_alternating_weeks() {
+        // 2021-03-02 is a Tuesday, so 월 of the first week is 2021-03-01
+        let semester = Semester {
+            start: NaiveDate::from_ymd_opt(2021, 3, 2).unwrap(),
+            end: NaiveDate::from_ymd_opt(2021, 3, 31).unwrap(),
+            holidays: vec![NaiveDate::from_ymd_opt(2021, 3, 24).unwrap()],
+        };
+        let division = Division {
+            id: "CH102".to_owned(),
+            name: "일반화학실험".to_owned(),
+            division: "A".to_owned(),
+            hour: vec![[2 * DAY + 13 * 60, 2 * DAY + 16 * 60]],
+            weeks: Weeks::Even,
+            ..Default::default()
+        };
+        let ics = to_ics("CH102 A", &[&division], &semester);
+
+        assert!(ics.contains("DTSTART;TZID=Asia/Seoul:20210310T130000\r\n"));
+        assert!(ics.contains("EXDATE;TZID=Asia/Seoul:20210317T130000\r\n"));
+        assert!(ics.contains("EXDATE;TZID=Asia/Seoul:20210324T130000\r\n"));
+        assert!(!ics.contains("20210303"));
+        assert!(ics.contains("EXDATE;TZID=Asia/Seoul:20210331T130000\r\n"));
+    }
+
+    #[test]
+    fn ics_date_range() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2021, m, d).unwrap();
+        let semester = Semester {
+            start: date(3, 2),
+            end: date(6, 18),
+            holidays: Vec::new(),
+        };
+        let division = Division {
+            id: "HSS010".to_owned(),
+            division: "A".to_owned(),
+            english: true,
+            hour: vec![[9 * 60, 10 * 60]],
+            dates: Some([date(4, 26), date(6, 18)]),
+            ..Default::default()
+        };
+        let ics = to_ics("HSS010 A", &[&division], &semester);
+
+        assert!(ics.contains("DTSTART;TZID=Asia/Seoul:20210426T090000\r\n"));
+        assert!(ics.contains("RRULE:FREQ=WEEKLY;UNTIL=20210618T145959Z\r\n"));
+        assert!(!ics.contains("EXDATE"));
+    }
+
+    #[test]
+    fn ics_until_midnight() {
+        let semester = Semester {
+            start: NaiveDate::from_ymd_opt(2021, 3, 2).unwrap(),
+            end: NaiveDate::from_ymd_opt(2021, 6, 18).unwrap(),
+            holidays: Vec::new(),
+        };
+        let division = Division {
+            id: "HSS001".to_owned(),
+            division: "A".to_owned(),
+            hour: vec![[DAY + 22 * 60, 2 * DAY]],
+            ..Default::default()
+        };
+        let ics = to_ics("HSS001 A", &[&division], &semester);
+
+        assert!(ics.contains("DTSTART;TZID=Asia/Seoul:20210302T220000\r\n"));
+        assert!(ics.contains("DTEND;TZID=Asia/Seoul:20210303T000000\r\n"));
+    }
+
+    #[test]
+    fn fold_simple() {
+        assert_eq!(fold("SUMMARY:a"), "SUMMARY:a\r\n");
+        let folded = fold(&format!("SUMMARY:{}", "가".repeat(30)));
+        assert!(folded.split("\r\n").all(|l| l.len() <= 75));
+        assert_eq!(
+            folded.replace("\r\n ", ""),
+            format!("SUMMARY:{}\r\n", "가".repeat(30))
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    ids::SubjectId,
+    input::read_delimited,
+    output::{Field, Schema},
+    report::{division_order, Table},
+    state::State,
+};
+
+/// Student id to subject to the division the student is enrolled in, if any. Divisions go by
+/// name, since the two sides of a diff need not share a catalog.
+pub type Assignment = BTreeMap<String, BTreeMap<SubjectId, Option<String>>>;
+
+/// Reads an output CSV laid out by `schema` with fields separated by `delimiter`, or a saved
+/// state if `path` ends in `.json`.
+pub fn read_assignment(path: &str, delimiter: u8, schema: &Schema) -> Assignment {
+    if path.ends_with(".json") {
+        let (subjects, students) = State::load(path).into_parts();
+        return students
+            .values()
+            .map(|s| {
+                let divisions = s
+                    .subjects
+                    .iter()
+                    .map(|(subject_id, status)| {
+                        let division = status.division();
+                        (subject_id, division.map(|d| subjects[d].division.clone()))
+                    })
+                    .collect();
+                (s.id.clone(), divisions)
+            })
+            .collect();
+    }
+
+    let (header, records) = read_delimited(path, "output", None, delimiter);
+    let field = |name: &str| {
+        schema
+            .0
+            .iter()
+            .find(|c| c.header == name.trim())
+            .map(|c| c.field.clone())
+    };
+    let fields: Vec<Option<Field>> = header.iter().map(field).collect();
+    let id_at = fields
+        .iter()
+        .position(|f| *f == Some(Field::Id))
+        .unwrap_or_else(|| panic!("{} has no student id column", path));
+
+    records
+        .iter()
+        .map(|r| {
+            let subjects = fields
+                .iter()
+                .zip(r.iter())
+                .filter_map(|(f, cell)| match f {
+                    Some(Field::Subject(subject_id)) => Some((*subject_id, parse_division(cell))),
+                    _ => None,
+                })
+                .collect();
+            (r[id_at].trim().to_owned(), subjects)
+        })
+        .collect()
+}
+
+/// Reads a status as `Status::name` shows it, keeping only the division.
+fn parse_division(s: &str) -> Option<String> {
+    match s.trim() {
+        "?" | "Undefined" | "-" => None,
+        division => Some(division.to_owned()),
+    }
+}
+
+/// The division `student` is enrolled in for `subject_id`, if any.
+fn division<'a>(
+    assignment: &'a Assignment,
+    student: &str,
+    subject_id: SubjectId,
+) -> Option<&'a str> {
+    assignment
+        .get(student)
+        .and_then(|s| s.get(&subject_id))
+        .and_then(|d| d.as_deref())
+}
+
+/// What changed from `old` to `new`.
+pub struct Diff {
+    /// (student, subject, old division, new division) for students who stayed enrolled.
+    pub moved: Vec<[String; 4]>,
+    /// (subject, division, old count, new count) for every division whose count changed.
+    pub counts: Vec<(String, String, usize, usize)>,
+    /// (student, subject, division) for students enrolled only in `new`.
+    pub assigned: Vec<[String; 3]>,
+    /// (student, subject, division) for students enrolled only in `old`.
+    pub unassigned: Vec<[String; 3]>,
+}
+
+pub fn diff(old: &Assignment, new: &Assignment) -> Diff {
+    let mut diff = Diff {
+        moved: Vec::new(),
+        counts: Vec::new(),
+        assigned: Vec::new(),
+        unassigned: Vec::new(),
+    };
+    let mut counts: BTreeMap<(SubjectId, String), (usize, usize)> = BTreeMap::new();
+
+    let ids: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    for id in ids {
+        for subject_id in SubjectId::all() {
+            let (before, after) = (division(old, id, subject_id), division(new, id, subject_id));
+
+            if let Some(d) = before {
+                counts.entry((subject_id, d.to_owned())).or_default().0 += 1;
+            }
+            if let Some(d) = after {
+                counts.entry((subject_id, d.to_owned())).or_default().1 += 1;
+            }
+
+            let row = |d: &str| [id.clone(), subject_id.name().to_owned(), d.to_owned()];
+            match (before, after) {
+                (Some(a), Some(b)) if a != b => diff.moved.push([
+                    id.clone(),
+                    subject_id.name().to_owned(),
+                    a.to_owned(),
+                    b.to_owned(),
+                ]),
+                (None, Some(b)) => diff.assigned.push(row(b)),
+                (Some(a), None) => diff.unassigned.push(row(a)),
+                _ => {}
+            }
+        }
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().filter(|(_, (a, b))| a != b).collect();
+    counts.sort_by(|((s, d), _), ((t, e), _)| (s, division_order(d)).cmp(&(t, division_order(e))));
+    diff.counts = counts
+        .into_iter()
+        .map(|((subject, division), (a, b))| (subject.name().to_owned(), division, a, b))
+        .collect();
+    diff
+}
+
+impl Diff {
+    pub fn to_markdown(&self) -> String {
+        let table = |header: &[&str], rows: Vec<Vec<String>>| {
+            Table {
+                header: header.iter().map(|&h| h.to_owned()).collect(),
+                rows,
+            }
+            .to_markdown()
+        };
+
+        format!(
+            "## 분반 변경 ({})\n\n{}\n## 분반별 인원 변화 ({})\n\n{}\n## 신규 배정 ({})\n\n{}\n## 배정 취소 ({})\n\n{}",
+            self.moved.len(),
+            table(
+                &["학번", "과목번호", "이전", "이후"],
+                self.moved.iter().map(|r| r.to_vec()).collect()
+            ),
+            self.counts.len(),
+            table(
+                &["과목번호", "분반", "이전", "이후", "증감"],
+                self.counts
+                    .iter()
+                    .map(|(s, d, a, b)| vec![
+                        s.clone(),
+                        d.clone(),
+                        a.to_string(),
+                        b.to_string(),
+                        format!("{:+}", *b as i64 - *a as i64),
+                    ])
+                    .collect()
+            ),
+            self.assigned.len(),
+            table(
+                &["학번", "과목번호", "분반"],
+                self.assigned.iter().map(|r| r.to_vec()).collect()
+            ),
+            self.unassigned.len(),
+            table(
+                &["학번", "과목번호", "분반"],
+                self.unassigned.iter().map(|r| r.to_vec()).collect()
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assignment(rows: &[(&str, &str, &str)]) -> Assignment {
+        let mut a = Assignment::new();
+        for &(id, subject, status) in rows {
+            a.entry(id.to_owned())
+                .or_default()
+                .insert(SubjectId::of(subject), parse_division(status));
+        }
+        a
+    }
+
+    #[test]
+    fn read_assignment_delimited() {
+        let path = std::env::temp_dir().join("saenaegi_read_assignment_delimited.csv");
+        std::fs::write(&path, "학번;이름;프밍기\n1;\"Kim; Gaya\";A\n2;Lee;?\n").unwrap();
+
+        let read = read_assignment(path.to_str().unwrap(), b';', &Schema::default());
+        assert_eq!(
+            read,
+            assignment(&[("1", "CS101", "A"), ("2", "CS101", "?")])
+        );
+    }
+
+    #[test]
+    fn diff_simple() {
+        let old = assignment(&[
+            ("1", "CS101", "A"),
+            ("2", "CS101", "A"),
+            ("3", "CS101", "?"),
+            ("1", "MAS101", "B"),
+        ]);
+        let new = assignment(&[
+            ("1", "CS101", "B"),
+            ("2", "CS101", "A"),
+            ("3", "CS101", "A"),
+            ("1", "MAS101", "-"),
+        ]);
+        let diff = diff(&old, &new);
+
+        assert_eq!(diff.moved, vec![["1", "CS101", "A", "B"].map(String::from)]);
+        assert_eq!(
+            diff.counts,
+            vec![
+                ("CS101".to_owned(), "B".to_owned(), 0, 1),
+                ("MAS101".to_owned(), "B".to_owned(), 1, 0),
+            ]
+        );
+        assert_eq!(diff.assigned, vec![["3", "CS101", "A"].map(String::from)]);
+        assert_eq!(
+            diff.unassigned,
+            vec![["1", "MAS101", "B"].map(String::from)]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use std::fs;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    passes::{Pass, PASSES},
+    state::State,
+    student::Students,
+    subject::Subjects,
+};
+
+const PATH: &str = "./checkpoints";
+
+/// Looks up comma-separated pass names, keeping the given order.
+pub fn plan(names: &str) -> Vec<(&'static str, Pass)> {
+    names
+        .split(',')
+        .map(|name| {
+            *PASSES
+                .iter()
+                .find(|(n, _)| *n == name.trim())
+                .unwrap_or_else(|| panic!("unknown pass {:?}", name))
+        })
+        .collect()
+}
+
+/// The passes of a full run that come after `pass`.
+pub fn after(pass: &str) -> Vec<(&'static str, Pass)> {
+    let i = PASSES
+        .iter()
+        .position(|(n, _)| *n == pass)
+        .unwrap_or_else(|| panic!("unknown pass {:?}", pass));
+    PASSES[i + 1..].to_vec()
+}
+
+pub fn checkpoint(pass: &str) -> String {
+    format!("{}/{}.json", PATH, pass)
+}
+
+/// Runs `plan` in order with an rng seeded by `seed`, saving a checkpoint after every pass.
+pub fn run(subjects: &mut Subjects, students: &mut Students, plan: &[(&str, Pass)], seed: u64) {
+    fs::create_dir_all(PATH).unwrap();
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    for (name, pass) in plan {
+        pass(subjects, students, &mut rng);
+
+        let mut state = State::new(subjects, students);
+        state.metadata.seed = Some(seed);
+        state.metadata.pass = Some(name.to_string());
+        state.save(&checkpoint(name));
+    }
+}
+
+/// Loads the checkpoint saved after `pass`.
+pub fn resume(pass: &str) -> (Subjects, Students) {
+    State::load(&checkpoint(pass)).into_parts()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(plan: &[(&'static str, Pass)]) -> Vec<&'static str> {
+        plan.iter().map(|(name, _)| *name).collect()
+    }
+
+    #[test]
+    fn plan_simple() {
+        assert_eq!(names(&plan("PH141, CS101")), vec!["PH141", "CS101"]);
+        assert_eq!(names(&after("HSS010")), vec!["CH102", "PH151"]);
+        assert!(after("PH151").is_empty());
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+
+use crate::student::SUBJECTS;
+
+/// A subject, as its position in `SUBJECTS`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SubjectId(u8);
+
+impl SubjectId {
+    /// Every subject, in the order of `SUBJECTS`.
+    pub fn all() -> impl Iterator<Item = SubjectId> {
+        (0..SUBJECTS.len()).map(|i| SubjectId(i as u8))
+    }
+
+    pub fn parse(name: &str) -> Option<SubjectId> {
+        SUBJECTS
+            .iter()
+            .position(|&s| s == name.trim())
+            .map(|i| SubjectId(i as u8))
+    }
+
+    /// The subject named `name`, which must be in `SUBJECTS`.
+    pub fn of(name: &str) -> SubjectId {
+        Self::parse(name).unwrap_or_else(|| panic!("unknown subject {:?}", name))
+    }
+
+    pub fn name(self) -> &'static str {
+        SUBJECTS[self.index()]
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for SubjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A division, as its position in `Subjects`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DivisionId(u32);
+
+impl DivisionId {
+    pub fn new(index: usize) -> Self {
+        DivisionId(index as u32)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A student, as their position in `Students`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct StudentId(u32);
+
+impl StudentId {
+    pub fn new(index: usize) -> Self {
+        StudentId(index as u32)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
@@ -0,0 +1,490 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::{
+    ids::{DivisionId, StudentId, SubjectId},
+    input::read_records,
+    passes::{assign, unassign},
+    student::{Status, Student, Students},
+    subject::Subjects,
+};
+
+/// A change to a published assignment.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Change {
+    /// A late enrollee, to be placed in every subject they are `Undefined` in.
+    Add(Box<Student>),
+    /// A student who withdrew, freeing all of their seats.
+    Withdraw(String),
+    /// A student's status in one subject changes. `Enroll` pins them to that division.
+    Status {
+        student: String,
+        subject: SubjectId,
+        status: Status,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    #[serde(rename = "구분")]
+    kind: String,
+    #[serde(rename = "학번")]
+    student: String,
+    #[serde(rename = "과목번호", default)]
+    subject: String,
+    #[serde(rename = "상태", default)]
+    status: String,
+}
+
+/// Reads rows of `철회` (withdrawal) and `변경` (status change, with `과목번호` and `상태` as in
+/// the student list: `Undefined`, `-` or a division).
+pub fn read_changes(path: &str, subjects: &Subjects) -> Vec<Change> {
+    let (header, records) = read_records(path, "changes", None);
+    records
+        .iter()
+        .map(|record| {
+            let row: Row = record.deserialize(Some(&header)).unwrap();
+            match row.kind.trim() {
+                "철회" => Change::Withdraw(row.student.trim().to_owned()),
+                "변경" => {
+                    let subject = SubjectId::parse(&row.subject)
+                        .unwrap_or_else(|| panic!("unknown subject {:?}", row.subject));
+                    Change::Status {
+                        student: row.student.trim().to_owned(),
+                        subject,
+                        status: Status::parse(&row.status, subject, subjects)
+                            .unwrap_or_else(|e| panic!("{}: {}", path, e)),
+                    }
+                }
+                other => panic!("unknown change {:?}", other),
+            }
+        })
+        .collect()
+}
+
+/// A student entering, leaving or switching a division, by name as it is printed and logged.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Move {
+    pub student: String,
+    pub subject: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl Move {
+    pub fn new(
+        student: &Student,
+        subject: SubjectId,
+        from: Option<DivisionId>,
+        to: Option<DivisionId>,
+        subjects: &Subjects,
+    ) -> Self {
+        let name = |d: DivisionId| subjects[d].division.clone();
+        Move {
+            student: student.id.clone(),
+            subject: subject.name().to_owned(),
+            from: from.map(name),
+            to: to.map(name),
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {} -> {}",
+            self.student,
+            self.subject,
+            self.from.as_deref().unwrap_or("-"),
+            self.to.as_deref().unwrap_or("-"),
+        )
+    }
+}
+
+/// (student, division) pairs a change asked for but could not have.
+pub type Refused = Vec<(StudentId, DivisionId)>;
+
+/// Applies `changes` in order, placing and removing only the students involved. Other students
+/// are moved to another division of the same subject only when a student cannot be placed
+/// otherwise. Returns the moves made, the (student, subject) pairs left unplaced, and the
+/// (student, division) pairs a change asked for but that were full or clashed with a division
+/// the student is pinned to. A late enrollee is placed like an undefined student in a subject
+/// whose given division is refused; a refused status change leaves the student as they were.
+pub fn apply(
+    subjects: &mut Subjects,
+    students: &mut Students,
+    changes: Vec<Change>,
+) -> (Vec<Move>, Vec<(StudentId, SubjectId)>, Refused) {
+    let mut moves = Vec::new();
+    let mut pending = Vec::new();
+    let mut unplaced = Vec::new();
+    let mut refused = Vec::new();
+
+    for change in changes {
+        match change {
+            Change::Add(mut student) => {
+                // counted towards their quota as in `passes::prepare`, one after another, so
+                // that a full division or one clashing with an earlier one is caught
+                let given: Vec<(SubjectId, DivisionId)> = student.subjects.enrolled().collect();
+                for &(subject_id, _) in &given {
+                    student.subjects[subject_id] = Status::Undefined;
+                }
+                student.occupancy.clear();
+                let mut refused_divisions = Vec::new();
+                for (subject_id, division_id) in given {
+                    let division = &subjects[division_id];
+                    if division.assigned < division.quota && student.fits(division_id, subjects) {
+                        assign(&mut student, division_id, subjects);
+                        moves.push(Move::new(
+                            &student,
+                            subject_id,
+                            None,
+                            Some(division_id),
+                            subjects,
+                        ));
+                    } else {
+                        student.pinned.remove(&subject_id);
+                        refused_divisions.push(division_id);
+                    }
+                }
+                let undefined: Vec<SubjectId> = SubjectId::all()
+                    .filter(|&subject_id| student.subjects[subject_id] == Status::Undefined)
+                    .collect();
+                let id = students.insert(*student);
+                pending.extend(undefined.into_iter().map(|subject_id| (id, subject_id)));
+                refused.extend(refused_divisions.into_iter().map(|d| (id, d)));
+            }
+            Change::Withdraw(name) => {
+                let id = students
+                    .find(&name)
+                    .unwrap_or_else(|| panic!("no student {}", name));
+                let mut student = students.remove(id);
+                let enrolled: Vec<_> = student.subjects.enrolled().collect();
+                for (subject_id, division_id) in enrolled {
+                    moves.push(Move::new(
+                        &student,
+                        subject_id,
+                        Some(division_id),
+                        None,
+                        subjects,
+                    ));
+                    unassign(&mut student, subject_id, subjects);
+                }
+                pending.retain(|&(s, _)| s != id);
+                unplaced.retain(|&(s, _)| s != id);
+                refused.retain(|&(s, _)| s != id);
+            }
+            Change::Status {
+                student: name,
+                subject: subject_id,
+                status,
+            } => {
+                let id = students
+                    .find(&name)
+                    .unwrap_or_else(|| panic!("no student {}", name));
+                let from = students[id].subjects[subject_id].division();
+
+                // checked before anything is released, so that a refused change costs nothing
+                if let Status::Enroll(division_id) = status {
+                    let (student, division) = (&students[id], &subjects[division_id]);
+                    let blocked = student.subjects.enrolled().any(|(other, d)| {
+                        other != subject_id
+                            && student.pinned.contains(&other)
+                            && !subjects[d].is_disjoint(division)
+                    });
+                    if blocked
+                        || (from != Some(division_id)
+                            && !make_room(subjects, students, id, division_id, &mut moves))
+                    {
+                        refused.push((id, division_id));
+                        continue;
+                    }
+                }
+
+                let student = &mut students[id];
+                unassign(student, subject_id, subjects);
+                student.pinned.remove(&subject_id);
+                pending.retain(|&(s, subject)| s != id || subject != subject_id);
+                unplaced.retain(|&(s, subject)| s != id || subject != subject_id);
+
+                match status {
+                    Status::Enroll(division_id) => {
+                        // the student's own divisions clashing with the new one are unpinned, and
+                        // give way to it
+                        let division = &subjects[division_id];
+                        let clashing: Vec<(SubjectId, DivisionId)> = student
+                            .subjects
+                            .enrolled()
+                            .filter(|&(other, d)| {
+                                other != subject_id && !subjects[d].is_disjoint(division)
+                            })
+                            .collect();
+                        for (other, d) in clashing {
+                            moves.push(Move::new(student, other, Some(d), None, subjects));
+                            unassign(student, other, subjects);
+                            pending.push((id, other));
+                        }
+
+                        assign(student, division_id, subjects);
+                        student.pinned.insert(subject_id);
+                        moves.push(Move::new(
+                            student,
+                            subject_id,
+                            from,
+                            Some(division_id),
+                            subjects,
+                        ));
+                    }
+                    Status::No => {
+                        student.subjects[subject_id] = Status::No;
+                        if from.is_some() {
+                            moves.push(Move::new(student, subject_id, from, None, subjects));
+                        }
+                    }
+                    Status::Undefined => {
+                        if from.is_some() {
+                            moves.push(Move::new(student, subject_id, from, None, subjects));
+                        }
+                        pending.push((id, subject_id));
+                    }
+                }
+            }
+        }
+    }
+
+    for (id, subject_id) in pending {
+        if !place(subjects, students, id, subject_id, &mut moves) {
+            unplaced.push((id, subject_id));
+        }
+    }
+    (moves, unplaced, refused)
+}
+
+/// Places a student in a division of `subject_id` with a free seat, or in a full one after
+/// moving one of its students to another division with a free seat.
+pub fn place(
+    subjects: &mut Subjects,
+    students: &mut Students,
+    id: StudentId,
+    subject_id: SubjectId,
+    moves: &mut Vec<Move>,
+) -> bool {
+    let mut division_ids: Vec<DivisionId> = subjects.of(subject_id).to_vec();
+    // emptiest first, to keep divisions balanced
+    division_ids.sort_by_key(|&d| subjects[d].assigned - subjects[d].quota);
+
+    let fitting: Vec<DivisionId> = division_ids
+        .into_iter()
+        .filter(|&d| students[id].fits(d, subjects))
+        .collect();
+
+    let free = fitting
+        .iter()
+        .copied()
+        .find(|&d| subjects[d].assigned < subjects[d].quota);
+    let target = free.or_else(|| {
+        fitting
+            .iter()
+            .copied()
+            .find(|&d| make_room(subjects, students, id, d, moves))
+    });
+
+    match target {
+        Some(division_id) => {
+            assign(&mut students[id], division_id, subjects);
+            moves.push(Move::new(
+                &students[id],
+                subject_id,
+                None,
+                Some(division_id),
+                subjects,
+            ));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Frees a seat in a full division by moving one of its students (other than `id`, and not
+/// pinned to it) to another division of the same subject. Returns whether there is a free seat.
+fn make_room(
+    subjects: &mut Subjects,
+    students: &mut Students,
+    id: StudentId,
+    division_id: DivisionId,
+    moves: &mut Vec<Move>,
+) -> bool {
+    let division = &subjects[division_id];
+    if division.assigned < division.quota {
+        return true;
+    }
+
+    let subject_id = division.subject;
+    let mut members: Vec<(StudentId, &Student)> = students
+        .iter()
+        .filter(|&(other, s)| {
+            other != id && !s.pinned.contains(&subject_id) && s.is_taking(division_id)
+        })
+        .collect();
+    members.sort_by(|a, b| a.1.id.cmp(&b.1.id));
+    let members: Vec<StudentId> = members.into_iter().map(|(member, _)| member).collect();
+
+    for member in members {
+        let destination = subjects.of(subject_id).iter().copied().find(|&d| {
+            d != division_id
+                && subjects[d].assigned < subjects[d].quota
+                && students[member].fits(d, subjects)
+        });
+        if let Some(destination) = destination {
+            let student = &mut students[member];
+            unassign(student, subject_id, subjects);
+            assign(student, destination, subjects);
+            moves.push(Move::new(
+                student,
+                subject_id,
+                Some(division_id),
+                Some(destination),
+                subjects,
+            ));
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::subject::Division;
+
+    fn division(id: &str, division: &str, quota: i32, hour: [i32; 2]) -> Division {
+        Division {
+            id: id.to_owned(),
+            division: division.to_owned(),
+            quota,
+            hour: vec![hour],
+            ..Default::default()
+        }
+    }
+
+    fn student(id: &str) -> Student {
+        Student {
+            id: id.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn apply_moves_others_only_if_needed() {
+        // CS101 A (09:00) has room for one, B (10:00) for two
+        // MAS101 A clashes with CS101 B
+        let mut subjects = Subjects::new(vec![
+            division("CS101", "A", 1, [540, 600]),
+            division("CS101", "B", 2, [600, 660]),
+            division("MAS101", "A", 2, [600, 660]),
+        ]);
+        let (cs101, mas101) = (SubjectId::of("CS101"), SubjectId::of("MAS101"));
+        let (cs101_a, cs101_b) = (subjects.division(cs101, "A"), subjects.division(cs101, "B"));
+
+        let mut old = student("1");
+        old.subjects[cs101] = Status::Enroll(cs101_a);
+        subjects[cs101_a].assigned = 1;
+        let mut students = Students::new(vec![old]);
+
+        // a late enrollee whose MAS101 division only leaves CS101 A
+        let mut new = student("2");
+        new.subjects[mas101] = Status::Enroll(subjects.division(mas101, "A"));
+        new.subjects[cs101] = Status::Undefined;
+
+        let (moves, unplaced, refused) = apply(
+            &mut subjects,
+            &mut students,
+            vec![Change::Add(Box::new(new))],
+        );
+        assert!(unplaced.is_empty() && refused.is_empty());
+        assert!(students[students.find("1").unwrap()].is_taking(cs101_b));
+        assert!(students[students.find("2").unwrap()].is_taking(cs101_a));
+        assert_eq!(moves.len(), 3);
+
+        let (moves, _, _) = apply(
+            &mut subjects,
+            &mut students,
+            vec![Change::Withdraw("1".to_owned())],
+        );
+        assert_eq!(
+            moves,
+            vec![Move {
+                student: "1".to_owned(),
+                subject: "CS101".to_owned(),
+                from: Some("B".to_owned()),
+                to: None,
+            }]
+        );
+        assert_eq!(subjects[cs101_b].assigned, 0);
+    }
+
+    #[test]
+    fn apply_refuses_full_or_clashing() {
+        // CS101 A (09:00) has room for one, B (10:00) for two; MAS101 A clashes with CS101 B
+        let mut subjects = Subjects::new(vec![
+            division("CS101", "A", 1, [540, 600]),
+            division("CS101", "B", 2, [600, 660]),
+            division("MAS101", "A", 2, [600, 660]),
+            division("MAS101", "B", 2, [720, 780]),
+        ]);
+        let (cs101, mas101) = (SubjectId::of("CS101"), SubjectId::of("MAS101"));
+        let (cs101_a, cs101_b) = (subjects.division(cs101, "A"), subjects.division(cs101, "B"));
+        let (mas101_a, mas101_b) = (
+            subjects.division(mas101, "A"),
+            subjects.division(mas101, "B"),
+        );
+
+        let mut old = student("1");
+        old.subjects[cs101] = Status::Enroll(cs101_a);
+        old.subjects[mas101] = Status::Enroll(mas101_a);
+        old.pinned.insert(mas101);
+        subjects[cs101_a].assigned = 1;
+        subjects[mas101_a].assigned = 1;
+        let mut students = Students::new(vec![old]);
+        let first = students.find("1").unwrap();
+
+        // CS101 B clashes with the pinned MAS101 A, so the student keeps CS101 A
+        let (moves, unplaced, refused) = apply(
+            &mut subjects,
+            &mut students,
+            vec![Change::Status {
+                student: "1".to_owned(),
+                subject: cs101,
+                status: Status::Enroll(cs101_b),
+            }],
+        );
+        assert!(moves.is_empty() && unplaced.is_empty());
+        assert_eq!(refused, vec![(first, cs101_b)]);
+        assert!(students[first].is_taking(cs101_a));
+        assert_eq!(subjects[cs101_a].assigned, 1);
+
+        // CS101 A is full, and MAS101 A clashes with CS101 B, given before it
+        let mut new = student("2");
+        new.subjects[cs101] = Status::Enroll(cs101_a);
+        new.subjects[mas101] = Status::Enroll(mas101_b);
+        new.pinned = [cs101, mas101].iter().copied().collect();
+        let mut other = student("3");
+        other.subjects[cs101] = Status::Enroll(cs101_b);
+        other.subjects[mas101] = Status::Enroll(mas101_a);
+        let (_, unplaced, refused) = apply(
+            &mut subjects,
+            &mut students,
+            vec![Change::Add(Box::new(new)), Change::Add(Box::new(other))],
+        );
+        let (second, third) = (students.find("2").unwrap(), students.find("3").unwrap());
+        assert!(unplaced.is_empty());
+        assert_eq!(refused, vec![(second, cs101_a), (third, mas101_a)]);
+        assert_eq!(subjects[cs101_a].assigned, 1);
+        // placed like undefined students instead
+        assert!(students[second].is_taking(cs101_b) && students[second].is_taking(mas101_b));
+        assert!(students[third].is_taking(cs101_b) && students[third].is_taking(mas101_b));
+        assert!(!students[second].pinned.contains(&cs101));
+    }
+}
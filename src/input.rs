@@ -0,0 +1,123 @@
+use std::fs;
+
+use calamine::{open_workbook_auto, Reader};
+use csv::StringRecord;
+use encoding_rs::{Encoding, EUC_KR, UTF_8};
+
+/// Reads the header and the rows of a source table. `.xlsx` (and other spreadsheet) files are
+/// read from the sheet named `sheet`; anything else is read as CSV and `sheet` is ignored.
+///
+/// CSV files are decoded with `encoding` (`"cp949"` or a WHATWG label such as `"euc-kr"`), or
+/// as UTF-8 if they are valid UTF-8 and EUC-KR otherwise. A byte order mark overrides both and
+/// is stripped.
+pub fn read_records(
+    path: &str,
+    sheet: &str,
+    encoding: Option<&str>,
+) -> (StringRecord, Vec<StringRecord>) {
+    read_delimited(path, sheet, encoding, b',')
+}
+
+/// `read_records` for CSV files whose fields are separated by `delimiter`.
+pub fn read_delimited(
+    path: &str,
+    sheet: &str,
+    encoding: Option<&str>,
+    delimiter: u8,
+) -> (StringRecord, Vec<StringRecord>) {
+    let extension = path.rsplit('.').next().unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "xlsx" | "xlsm" | "xls" | "ods" => read_sheet(path, sheet),
+        _ => {
+            let text = decode(&fs::read(path).unwrap(), encoding);
+            let mut reader = csv::ReaderBuilder::new()
+                .delimiter(delimiter)
+                .from_reader(text.as_bytes());
+            let header = reader.headers().unwrap().clone();
+            (header, reader.records().map(|r| r.unwrap()).collect())
+        }
+    }
+}
+
+fn decode(bytes: &[u8], encoding: Option<&str>) -> String {
+    let encoding = match encoding {
+        // not a WHATWG label, but what Korean office tools call it
+        Some(label) if label.eq_ignore_ascii_case("cp949") => EUC_KR,
+        Some(label) => Encoding::for_label(label.as_bytes())
+            .unwrap_or_else(|| panic!("unknown encoding {:?}", label)),
+        None if std::str::from_utf8(bytes).is_ok() => UTF_8,
+        // CP949 is decoded by the WHATWG EUC-KR decoder
+        None => EUC_KR,
+    };
+
+    let (text, used, malformed) = encoding.decode(bytes);
+    assert!(!malformed, "input is not valid {}", used.name());
+    text.into_owned()
+}
+
+fn read_sheet(path: &str, sheet: &str) -> (StringRecord, Vec<StringRecord>) {
+    let mut workbook = open_workbook_auto(path).unwrap();
+    let range = workbook
+        .worksheet_range(sheet)
+        .unwrap_or_else(|e| panic!("{} has no sheet {:?}: {}", path, sheet, e));
+
+    let mut rows = range
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|cell| cell.to_string())
+                .collect::<StringRecord>()
+        })
+        // rows the registrar leaves blank between sections
+        .filter(|record| record.iter().any(|cell| !cell.trim().is_empty()));
+    let header = rows.next().unwrap_or_default();
+    (header, rows.collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rust_xlsxwriter::Workbook;
+
+    #[test]
+    fn read_sheet_simple() {
+        let path = std::env::temp_dir().join("saenaegi_read_sheet_simple.xlsx");
+        let path = path.to_str().unwrap();
+
+        let mut workbook = Workbook::new();
+        workbook.add_worksheet().set_name("다른 시트").unwrap();
+        let sheet = workbook.add_worksheet().set_name("과목").unwrap();
+        sheet.write_string(0, 0, "분반").unwrap();
+        sheet.write_string(0, 1, "정원").unwrap();
+        sheet.write_string(0, 2, "강의시간").unwrap();
+        sheet.write_string(1, 0, "A").unwrap();
+        sheet.write_number(1, 1, 25).unwrap();
+        sheet
+            .write_string(1, 2, "월 09:00~10:30\n수 09:00~10:30")
+            .unwrap();
+        workbook.save(path).unwrap();
+
+        let (header, records) = read_records(path, "과목", None);
+        assert_eq!(header, StringRecord::from(vec!["분반", "정원", "강의시간"]));
+        assert_eq!(
+            records,
+            vec![StringRecord::from(vec![
+                "A",
+                "25",
+                "월 09:00~10:30\n수 09:00~10:30"
+            ])]
+        );
+    }
+
+    #[test]
+    fn decode_simple() {
+        let (euc_kr, _, _) = EUC_KR.encode("과목번호,분반");
+        assert_eq!(decode(&euc_kr, None), "과목번호,분반");
+        assert_eq!(decode(&euc_kr, Some("cp949")), "과목번호,분반");
+        assert_eq!(decode("\u{feff}과목번호".as_bytes(), None), "과목번호");
+        assert_eq!(
+            decode("\u{feff}과목번호".as_bytes(), Some("euc-kr")),
+            "과목번호"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::{collections::BTreeMap, fs};
+
+use crate::{
+    report::Table,
+    subject::{Division, Subjects},
+};
+
+const PATH_CSV: &str = "./instructors.csv";
+const PATH_MARKDOWN: &str = "./instructors.md";
+
+/// Every division with an instructor, by instructor, in catalog order.
+fn by_instructor(subjects: &Subjects) -> BTreeMap<&str, Vec<&Division>> {
+    let mut map: BTreeMap<&str, Vec<&Division>> = BTreeMap::new();
+    for (_, division) in subjects.iter() {
+        for instructor in &division.instructors {
+            map.entry(instructor.as_str()).or_default().push(division);
+        }
+    }
+    map
+}
+
+fn name(division: &Division) -> String {
+    format!("{} {}", division.id, division.division)
+}
+
+/// One row per instructor with their divisions, the hours they teach in a week their divisions
+/// meet, and how many students they teach.
+pub fn load(subjects: &Subjects) -> Table {
+    let header = ["교수", "분반", "분반 수", "주당 시간", "학생 수"]
+        .iter()
+        .map(|&s| s.to_owned())
+        .collect();
+
+    let rows = by_instructor(subjects)
+        .into_iter()
+        .map(|(instructor, divisions)| {
+            let minutes: i32 = divisions
+                .iter()
+                .flat_map(|d| d.hour.iter().map(|h| h[1] - h[0]))
+                .sum();
+            vec![
+                instructor.to_owned(),
+                divisions
+                    .iter()
+                    .map(|&d| name(d))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                divisions.len().to_string(),
+                format!("{:.1}", minutes as f64 / 60.0),
+                divisions
+                    .iter()
+                    .map(|d| d.assigned)
+                    .sum::<i32>()
+                    .to_string(),
+            ]
+        })
+        .collect();
+
+    Table { header, rows }
+}
+
+pub fn write_load(subjects: &Subjects) {
+    let table = load(subjects);
+    table.write_csv(PATH_CSV);
+    fs::write(PATH_MARKDOWN, table.to_markdown()).unwrap();
+}
+
+/// (instructor, division, division) for every instructor teaching two divisions at once.
+pub fn double_bookings(subjects: &Subjects) -> Vec<(String, String, String)> {
+    let mut clashes = Vec::new();
+    for (instructor, divisions) in by_instructor(subjects) {
+        for (i, lhs) in divisions.iter().enumerate() {
+            for rhs in &divisions[i + 1..] {
+                if !lhs.is_disjoint(rhs) {
+                    clashes.push((instructor.to_owned(), name(lhs), name(rhs)));
+                }
+            }
+        }
+    }
+    clashes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn division(id: &str, assigned: i32, hour: [i32; 2], instructors: &[&str]) -> Division {
+        Division {
+            id: id.to_owned(),
+            division: "A".to_owned(),
+            assigned,
+            quota: 40,
+            hour: vec![hour],
+            instructors: instructors.iter().map(|&s| s.to_owned()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn load_and_double_bookings() {
+        let subjects = Subjects::new(vec![
+            division("CS101", 30, [540, 630], &["김교수"]),
+            division("CH101", 20, [600, 690], &["김교수", "이교수"]),
+            division("MAS101", 10, [720, 810], &["이교수"]),
+        ]);
+
+        assert_eq!(
+            load(&subjects).rows,
+            vec![
+                vec!["김교수", "CS101 A, CH101 A", "2", "3.0", "50"],
+                vec!["이교수", "CH101 A, MAS101 A", "2", "3.0", "30"],
+            ]
+        );
+        assert_eq!(
+            double_bookings(&subjects),
+            vec![(
+                "김교수".to_owned(),
+                "CS101 A".to_owned(),
+                "CH101 A".to_owned()
+            )]
+        );
+    }
+}
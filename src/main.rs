@@ -1,12 +1,33 @@
+mod calendar;
+mod diff;
+mod engine;
+mod ids;
+mod incremental;
+mod input;
+mod instructors;
+mod output;
+mod passes;
+mod reoptimize;
+mod report;
+mod rooms;
+mod roster;
+mod score;
+mod search;
+mod state;
 mod student;
 mod subject;
+mod swap;
+mod timetable;
+mod waitlist;
+mod workbook;
 
-use std::collections::{BTreeMap, HashMap};
+use std::{env, thread};
 
 use rand::prelude::*;
 
-use student::{Status, Student};
-use subject::Division;
+use state::State;
+use student::{Student, Students};
+use subject::Subjects;
 
 // 총 731명
 // const SUBJECTS: &[&str] = &[
@@ -28,517 +49,316 @@ use subject::Division;
 // ];
 
 fn main() {
-    let mut rng = thread_rng();
-    let rng = &mut rng;
-
-    let mut subjects = Division::read_csv();
-    let mut students = Student::read_csv();
-
-    for division in subjects.values().flat_map(|divisions| divisions.values()) {
-        assert_eq!(division.assigned, 0);
-    }
-
-    // 외국인, 영어 * 4, 고물, 실물, 일생 배정 정원 반영 및 수정
-    for student in students.values() {
-        let assigned_subjects: Vec<&Division> = student
-            .subjects
-            .iter()
-            .filter_map(|(subject_id, status)| {
-                if let Status::Enroll(division) = status {
-                    Some(&subjects[subject_id][division])
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        for i in 0..assigned_subjects.len() {
-            for j in i + 1..assigned_subjects.len() {
-                assert!(assigned_subjects[i].is_disjoint(assigned_subjects[j]));
-            }
-        }
-
-        for (subject, status) in &student.subjects {
-            if let Status::Enroll(division) = status {
-                subjects
-                    .get_mut(subject)
-                    .unwrap()
-                    .get_mut(division.trim())
-                    .unwrap()
-                    .assigned += 1;
-            }
+    let args: Vec<String> = env::args().collect();
+    let flag = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .map(|i| args.get(i + 1).unwrap_or_else(|| usage()).as_str())
+    };
+    let seed = flag("--seed")
+        .map(|s| s.parse().unwrap_or_else(|_| usage()))
+        .unwrap_or_else(|| thread_rng().gen());
+    let destination = output::Output {
+        path: flag("--output").unwrap_or(output::PATH).to_owned(),
+        delimiter: flag("--delimiter").map_or(output::DELIMITER, |d| {
+            output::parse_delimiter(d).unwrap_or_else(|| usage())
+        }),
+    };
+
+    // a leading flag is one of `run`'s
+    let command = args
+        .get(1)
+        .map(String::as_str)
+        .filter(|a| !a.starts_with("--"));
+    match command {
+        None | Some("run") => {
+            let mut subjects = Subjects::read();
+            let mut students = Student::read(&subjects);
+            passes::prepare(&mut subjects, &students);
+
+            let plan = flag("--plan").map_or_else(|| passes::PASSES.to_vec(), engine::plan);
+            finish(&mut subjects, &mut students, &plan, seed, &destination);
         }
-    }
-
-    // HSS022
-    {
-        subjects
-            .get_mut("HSS022")
-            .unwrap()
-            .get_mut("A")
-            .unwrap()
-            .quota = 25;
-        subjects
-            .get_mut("HSS022")
-            .unwrap()
-            .get_mut("C")
-            .unwrap()
-            .quota = 25;
-
-        let division_c: Vec<_> = Division::read_hss022_students()
-            .choose_multiple(rng, 25)
-            .collect();
-
-        for &student in Division::read_hss022_students() {
-            assign(
-                students.get_mut(student).unwrap(),
-                "HSS022",
-                if division_c.contains(&&student) {
-                    "C"
-                } else {
-                    "A"
+        Some("search") => {
+            let count = args
+                .get(2)
+                .and_then(|n| n.parse().ok())
+                .filter(|&n: &usize| n > 0)
+                .unwrap_or_else(|| usage());
+            let threads = flag("--threads").map_or_else(
+                || thread::available_parallelism().map_or(1, |n| n.get()),
+                |n| n.parse().unwrap_or_else(|_| usage()),
+            );
+            let mut subjects = Subjects::read();
+            let mut students = Student::read(&subjects);
+            passes::prepare(&mut subjects, &students);
+
+            let plan = flag("--plan").map_or_else(|| passes::PASSES.to_vec(), engine::plan);
+            let (weights, preferences) = (
+                score::Weights::read(),
+                score::read_preferences(&subjects, &students),
+            );
+            let runs = search::search(
+                &subjects,
+                &students,
+                &plan,
+                &search::seeds(seed, count),
+                threads,
+                &|subjects, students| {
+                    score::Metrics::measure(subjects, students, &preferences).score(&weights)
                 },
-                &mut subjects,
             );
-        }
-    }
-
-    // CS101
-    {
-        let mut cs101_students = students
-            .values_mut()
-            .filter(|student| student.subjects["CS101"] == Status::Undefined)
-            .collect::<Vec<_>>();
-        cs101_students.shuffle(rng);
-
-        let mut count = 1;
-        'next_student: for student in cs101_students {
-            count += 1;
-            for division in 'A'..='J' {
-                let division = division.to_string();
-                if student
-                    .subjects(&subjects)
-                    .iter()
-                    .all(|div| div.is_disjoint(&subjects["CS101"][&division]))
-                    && subjects["CS101"][&division].quota > subjects["CS101"][&division].assigned
-                {
-                    assign(student, "CS101", &division, &mut subjects);
-                    continue 'next_student;
-                }
+            print!("{}", search::distribution(&runs).to_markdown());
+            let scores: Vec<f64> = runs.iter().filter_map(|r| r.score).collect();
+            if scores.is_empty() {
+                panic!("all {} runs failed", runs.len());
             }
-            panic!("망함.. {} {:?}", count, student);
+            let best = runs[0];
+            println!(
+                "best of {} runs is seed {}: score {:.2} (median {:.2}, worst {:.2}), {} failed",
+                scores.len(),
+                best.seed,
+                scores[0],
+                scores[scores.len() / 2],
+                scores[scores.len() - 1],
+                runs.len() - scores.len()
+            );
+            finish(&mut subjects, &mut students, &plan, best.seed, &destination);
         }
+        Some("resume") => {
+            let pass = args.get(2).unwrap_or_else(|| usage());
+            let (mut subjects, mut students) = engine::resume(pass);
 
-        println!(
-            "Assigned {} students to CS101",
-            students
-                .values()
-                .filter(|student| matches!(student.subjects["CS101"], Status::Enroll(_)))
-                .count()
-        );
-    }
-
-    // MAS101, CH101
-    {
-        for division_char in 'A'..='P' {
-            if division_char == 'K' || division_char == 'L' {
-                continue;
+            let plan = flag("--plan").map_or_else(|| engine::after(pass), engine::plan);
+            finish(&mut subjects, &mut students, &plan, seed, &destination);
+        }
+        Some("update") => {
+            let changes = args.get(2).unwrap_or_else(|| usage());
+            let (mut subjects, mut students) =
+                State::load(flag("--state").unwrap_or(state::PATH)).into_parts();
+
+            let mut changes = incremental::read_changes(changes, &subjects);
+            if let Some(path) = flag("--students") {
+                let added = Student::read_from(path, "students", None, &subjects);
+                changes.splice(
+                    0..0,
+                    added
+                        .values()
+                        .map(|s| incremental::Change::Add(Box::new(s.clone()))),
+                );
             }
-            let division_id = division_char.to_string();
-            let division = &subjects["MAS101"][&division_id];
-
-            for student in students
-                .values_mut()
-                .filter(|s| {
-                    matches!(s.subjects["MAS101"], Status::Undefined)
-                        && s.subjects(&subjects)
-                            .iter()
-                            .all(|d| d.is_disjoint(division))
-                })
-                .choose_multiple(rng, (division.quota - division.assigned) as usize)
-            {
-                assign(student, "MAS101", &division_id, &mut subjects);
-
-                let mut candidates = match division_char {
-                    'A' | 'B' | 'C' | 'D' | 'E' | 'F' | 'G' | 'H' => vec!["A", "B", "C", "D"],
-                    'I' | 'J' | 'M' | 'N' | 'O' | 'P' => vec!["E", "F", "G", "H"],
-                    _ => unreachable!(),
-                };
-
-                candidates.retain(|&d| subjects["CH101"][d].quota > subjects["CH101"][d].assigned);
-                // println!("{:?}", candidates);
 
-                assign(
-                    student,
-                    "CH101",
-                    candidates
-                        .choose(rng)
-                        .expect(&format!("{:?} {:?}", subjects["MAS101"], subjects["CH101"])),
-                    &mut subjects,
+            let (moves, unplaced, refused) =
+                incremental::apply(&mut subjects, &mut students, changes);
+            for &(student, subject) in &unplaced {
+                println!("{} {}: unplaced", students[student].id, subject);
+            }
+            for &(student, division) in &refused {
+                let division = &subjects[division];
+                println!(
+                    "{} {} {}: refused, full or clashing",
+                    students[student].id, division.subject, division.division
                 );
             }
+            publish(&mut subjects, &mut students, &moves, "변경", &destination);
         }
+        Some("reoptimize") => {
+            let path = args.get(2).unwrap_or_else(|| usage());
+            let (mut subjects, mut students) =
+                State::load(flag("--state").unwrap_or(state::PATH)).into_parts();
+            let edits = reoptimize::read_edits(path, &subjects);
+
+            let (weights, preferences) = (
+                score::Weights::read(),
+                score::read_preferences(&subjects, &students),
+            );
+            let before = score::Metrics::measure(&subjects, &students, &preferences);
 
-        for &subject_id in &["MAS101", "CH101"] {
-            let count = students
-                .values()
-                .filter(|student| matches!(student.subjects[subject_id], Status::Enroll(_)))
-                .count();
-            assert_eq!(count, 728);
-            println!("Assigned {} students to {}", count, subject_id);
-        }
-
-        // println!("{:?}", subjects["CH101"].iter().collect::<BTreeMap<_, _>>());
-    }
-
-    // PH141
-    {
-        for student in students.values_mut().filter(|student| {
-            (student.is_taking("HSS011", "B")
-                || student.is_taking("CS101", "C")
-                || student.is_taking("CS101", "D")
-                || student.is_taking("CS101", "G")
-                || student.is_taking("CS101", "H"))
-                && !student.is_taking("PH161", "고급물")
-                && !student.is_taking("PH171", "체감형물")
-        }) {
-            let division = ["A", "B", "C"].choose(rng).unwrap();
-            assign(student, "PH141", division, &mut subjects);
-        }
-
-        for student in students.values_mut().filter(|student| {
-            matches!(student.subjects["PH141"], Status::Undefined)
-                && matches!(student.subjects["BS120"], Status::Enroll(_))
-        }) {
-            let mut division = ["D", "E", "F", "G"].choose(rng).unwrap();
-
-            while subjects["PH141"][*division].assigned >= subjects["PH141"][*division].quota {
-                division = ["D", "E", "F", "G"].choose(rng).unwrap();
+            let (moves, unplaced, overfull) =
+                reoptimize::reoptimize(&mut subjects, &mut students, edits);
+            for &(student, subject) in &unplaced {
+                println!("{} {}: unplaced", students[student].id, subject);
             }
-
-            assign(student, "PH141", division, &mut subjects);
+            for &division in &overfull {
+                let division = &subjects[division];
+                println!(
+                    "{} {}: {} assigned over a quota of {}, too many pinned",
+                    division.subject, division.division, division.assigned, division.quota
+                );
+            }
+            println!("{} placements changed", moves.len());
+            println!(
+                "score {:.2} -> {:.2}",
+                before.score(&weights),
+                score::Metrics::measure(&subjects, &students, &preferences).score(&weights)
+            );
+            publish(&mut subjects, &mut students, &moves, "재배정", &destination);
         }
-
-        for &division in &["A", "B", "C"] {
-            for student in students
-                .values_mut()
-                .filter(|student| matches!(student.subjects["PH141"], Status::Undefined))
-                .choose_multiple(
-                    rng,
-                    (subjects["PH141"][division].quota - subjects["PH141"][division].assigned)
-                        as usize,
-                )
-            {
-                assign(student, "PH141", division, &mut subjects);
+        Some("swap") => {
+            let path = args.get(2).unwrap_or_else(|| usage());
+            let (mut subjects, mut students) =
+                State::load(flag("--state").unwrap_or(state::PATH)).into_parts();
+            let requests = swap::read_requests(path, &subjects, &students);
+
+            let (moves, ungranted) = swap::trade(&mut subjects, &mut students, requests);
+            for r in &ungranted {
+                println!(
+                    "{} {}: {} -> {} waitlisted",
+                    students[r.student].id,
+                    r.subject,
+                    subjects[r.from].division,
+                    subjects[r.to].division
+                );
+                waitlist::enqueue(&mut subjects, r.student, r.to);
             }
+            publish(&mut subjects, &mut students, &moves, "교환", &destination);
         }
+        Some("waitlist") => {
+            let (mut subjects, mut students) =
+                State::load(flag("--state").unwrap_or(state::PATH)).into_parts();
 
-        for &division in &["A", "B", "C", "D", "E", "F", "G"] {
-            for student in students
-                .values_mut()
-                .filter(|student| {
-                    matches!(student.subjects["PH141"], Status::Undefined)
-                        && student
-                            .subjects(&subjects)
-                            .iter()
-                            .all(|d| d.is_disjoint(&subjects["PH141"][division]))
-                })
-                .choose_multiple(
-                    rng,
-                    (subjects["PH141"][division].quota - subjects["PH141"][division].assigned)
-                        as usize,
-                )
-            {
-                assign(student, "PH141", division, &mut subjects);
+            if let Some(path) = args.get(2).filter(|a| !a.starts_with("--")) {
+                for (student, division) in waitlist::read_preferences(path, &subjects, &students) {
+                    waitlist::enqueue(&mut subjects, student, division);
+                }
             }
+            publish(&mut subjects, &mut students, &[], "", &destination);
         }
-
-        println!(
-            "Assigned {} students to PH141, PH161, PH171",
-            students
-                .values()
-                .filter(
-                    |student| matches!(student.subjects["PH141"], Status::Enroll(_))
-                        || matches!(student.subjects["PH161"], Status::Enroll(_))
-                        || matches!(student.subjects["PH171"], Status::Enroll(_))
-                )
-                .count()
-        );
-
-        for s in students.values().filter(|student| {
-            !(matches!(student.subjects["PH141"], Status::Enroll(_))
-                || matches!(student.subjects["PH161"], Status::Enroll(_))
-                || matches!(student.subjects["PH171"], Status::Enroll(_)))
-        }) {
-            println!("{:?}", s.subjects.iter().collect::<BTreeMap<_, _>>());
+        Some("export") => {
+            let path = args
+                .get(2)
+                .filter(|a| !a.starts_with("--"))
+                .map_or(state::PATH, String::as_str);
+            let (subjects, students) = State::load(path).into_parts();
+            write_outputs(&subjects, &students, &destination);
         }
-    }
-
-    // HSS010
-    {
-        let to_be = students
-            .values()
-            .filter(|s| s.subjects["HSS010"] == Status::Undefined)
-            .count();
+        Some("check") => {
+            let path = args.get(2).map(String::as_str).unwrap_or(state::PATH);
+            let (subjects, _) = State::load(path).into_parts();
 
-        for &division_id in [
-            "A", "D", "B", "E", "C", "F", //
-            "N", "O", "P", //
-            "T", "V", "U", "W", "AB", //
-            "G", "H", "I", "J", //
-            "K", "L", "M", "Y", //
-            "Q", "R", "S", "Z", "AA", //
-        ]
-        .iter()
-        {
-            let division = &subjects["HSS010"][division_id];
-            for student in students
-                .values_mut()
-                .filter(|s| {
-                    matches!(s.subjects["HSS010"], Status::Undefined)
-                        && s.subjects(&subjects)
-                            .iter()
-                            .all(|d| d.is_disjoint(division))
-                        && matches!(s.subjects["CS101"], Status::Enroll(_))
-                        && !s.is_taking("CS101", "I")
-                        && !s.is_taking("CS101", "J")
-                })
-                .choose_multiple(rng, (division.quota - division.assigned) as usize)
-            {
-                assign(student, "HSS010", &division_id, &mut subjects);
+            let capacities = rooms::read_capacities();
+            if capacities.is_none() {
+                println!("no room file, capacities not checked");
             }
-        }
-
-        for &division_id in [
-            "D", "A", "E", "B", "F", "C", //
-            "T", "U", "AB", //
-            "Q", "R", "S", "Z", "AA", //
-            "K", "L", "M", "Y", //
-            "G", "H", "I", "J", //
-            "V", "W", //
-            "N", "O", "P", //
-        ]
-        .iter()
-        {
-            let division = &subjects["HSS010"][division_id];
-            for student in students
-                .values_mut()
-                .filter(|s| {
-                    matches!(s.subjects["HSS010"], Status::Undefined)
-                        && s.subjects(&subjects)
-                            .iter()
-                            .all(|d| d.is_disjoint(division))
-                })
-                .choose_multiple(rng, (division.quota - division.assigned) as usize)
-            {
-                assign(student, "HSS010", &division_id, &mut subjects);
+            let problems = rooms::check(&subjects, capacities.as_ref());
+            for problem in &problems {
+                println!("{}", problem);
             }
-        }
-
-        println!(
-            "Assigned {}/{} students to HSS010",
-            subjects["HSS010"].values().map(|d| d.assigned).sum::<i32>(),
-            to_be,
-        );
-    }
-
-    {
-        for &division_id in &[
-            "V", "J", 
-            "W", "K", 
-            "X", "L",
-
-            "D", "G", "P", "S",
-            "E", "H", "Q", "T",
-            "F", "I", "R", "U",
-
-            "M", "A",
-            "N", "B",
-            "O", "C",
-
-            "AA", "Y", "AB", "Z", "AC", //
-        ] {
-            let division = &subjects["CH102"][division_id];
-
-            for student in students
-                .values_mut()
-                .filter(|s| {
-                    matches!(s.subjects["CH102"], Status::Undefined)
-                        && s.subjects(&subjects)
-                            .iter()
-                            .all(|d| d.is_disjoint(division))
-                        && s.subjects["CS101"] != Status::No
-                })
-                .choose_multiple(rng, (division.quota - division.assigned) as usize)
-            {
-                assign(student, "CH102", &division_id, &mut subjects);
+            let clashes = instructors::double_bookings(&subjects);
+            for (instructor, lhs, rhs) in &clashes {
+                println!("{} teaches {} and {} at once", instructor, lhs, rhs);
             }
+            println!("{} problems", problems.len() + clashes.len());
         }
-
-        for &division_id in &[
-            "M", "V", "J", "A",
-            "N", "W", "K", "B",
-            "O", "X", "L", "C",
-
-            "D", "G", "P", "S",
-            "E", "H", "Q", "T",
-            "F", "I", "R", "U",
-
-            "AA", "Y", "AB", "Z", "AC", //
-        ] {
-            let division = &subjects["CH102"][division_id];
-
-            for student in students
-                .values_mut()
-                .filter(|s| {
-                    matches!(s.subjects["CH102"], Status::Undefined)
-                        && s.subjects(&subjects)
-                            .iter()
-                            .all(|d| d.is_disjoint(division))
-                })
-                .choose_multiple(rng, (division.quota - division.assigned) as usize)
-            {
-                assign(student, "CH102", &division_id, &mut subjects);
-            }
+        Some("score") => {
+            let path = args.get(2).map(String::as_str).unwrap_or(state::PATH);
+            let (subjects, students) = State::load(path).into_parts();
+
+            let preferences = score::read_preferences(&subjects, &students);
+            let metrics = score::Metrics::measure(&subjects, &students, &preferences);
+            print!("{}", metrics.table(&score::Weights::read()).to_markdown());
+            println!();
+            print!("{}", score::fill_variance_table(&subjects).to_markdown());
         }
-
-        println!(
-            "Assigned {}/{} students to CH102",
-            subjects["CH102"].values().map(|d| d.assigned).sum::<i32>(),
-            students.len(),
-        );
-    }
-
-    for &div in &["A", "C", "F", "H", "K", "M", "P", "R", "T", "W"] {
-        println!(
-            "{} {} + {}",
-            div,
-            students
-                .values()
-                .filter(|s| s
-                    .subjects(&subjects)
-                    .iter()
-                    .all(|d| d.is_disjoint(&subjects["PH151"][div]))
-                    && s.subjects["PH151"] == Status::Undefined
-                    && s.subjects["CS101"] != Status::No
-                )
-                .count(),
-            students
-                .values()
-                .filter(|s| s
-                    .subjects(&subjects)
-                    .iter()
-                    .all(|d| d.is_disjoint(&subjects["PH151"][div]))
-                    && s.subjects["PH151"] == Status::Undefined
-                    && s.subjects["CS101"] == Status::No
-                )
-                .count()
-        );
+        Some("diff") => {
+            let schema = output::Schema::read();
+            let old = diff::read_assignment(
+                args.get(2).unwrap_or_else(|| usage()),
+                destination.delimiter,
+                &schema,
+            );
+            let new = diff::read_assignment(
+                args.get(3).unwrap_or_else(|| usage()),
+                destination.delimiter,
+                &schema,
+            );
+            print!("{}", diff::diff(&old, &new).to_markdown());
+        }
+        Some(_) => usage(),
     }
+}
 
-    {
-        let to_be = subjects["PH151"].values().map(|d| d.quota).sum::<i32>();
-
-        for &division_id in &[
-            "K", "L", "M", //
-            "N", "O", //
-            "A", "B", //
-            "F", "G", //
-            "P", "Q", //
-            "C", "D", "E", //
-            "T", "U", "V", //
-            "W", "X", "Y", //
-            "H", "I", "J", //
-            "R", "S", //
-        ] {
-            let division = &subjects["PH151"][division_id];
-
-            for student in students
-                .values_mut()
-                .filter(|s| {
-                    matches!(s.subjects["PH151"], Status::Undefined)
-                        && s.subjects(&subjects)
-                            .iter()
-                            .all(|d| d.is_disjoint(division))
-                        && s.subjects["CS101"] == Status::No
-                })
-                .choose_multiple(rng, (division.quota - division.assigned) as usize)
-            {
-                assign(student, "PH151", &division_id, &mut subjects);
-            }
-        }
-        for &division_id in &[
-            "K", "L", "M", //
-            "N", "O", //
-            "C", "D", "E", //
-            "A", "B", //
-            "H", "I", "J", //
-            "R", "S", //
-            "T", "U", "V", //
-            "W", "X", "Y", //
-            "F", "G", //
-            "P", "Q", //
-        ] {
-            let division = &subjects["PH151"][division_id];
+fn usage() -> ! {
+    eprintln!("usage: saenaegi [run] [--seed <n>] [--plan <pass>,...]");
+    eprintln!("       saenaegi search <runs> [--seed <n>] [--plan <pass>,...] [--threads <n>]");
+    eprintln!("       saenaegi resume <pass> [--seed <n>] [--plan <pass>,...]");
+    eprintln!("       saenaegi update <changes.csv> [--students <students.csv>] [--state <state.json>]");
+    eprintln!("       saenaegi reoptimize <edits.csv> [--state <state.json>]");
+    eprintln!("       saenaegi swap <requests.csv> [--state <state.json>]");
+    eprintln!("       saenaegi waitlist [<preferences.csv>] [--state <state.json>]");
+    eprintln!("       saenaegi export [state.json] [--output <path>] [--delimiter <c|tab>]");
+    eprintln!("       saenaegi check [state.json]");
+    eprintln!("       saenaegi score [state.json]");
+    eprintln!("       saenaegi diff <old.csv|old.json> <new.csv|new.json> [--delimiter <c|tab>]");
+    eprintln!();
+    eprintln!("every command writing the assignment also takes --output and --delimiter");
+    eprintln!();
+    eprintln!(
+        "passes: {}",
+        passes::PASSES
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    std::process::exit(2);
+}
 
-            for student in students
-                .values_mut()
-                .filter(|s| {
-                    matches!(s.subjects["PH151"], Status::Undefined)
-                        && s.subjects(&subjects)
-                            .iter()
-                            .all(|d| d.is_disjoint(division))
-                })
-                .choose_multiple(rng, (division.quota - division.assigned) as usize)
-            {
-                assign(student, "PH151", &division_id, &mut subjects);
-            }
-        }
+/// Runs `plan`, then saves the state and writes every output.
+fn finish(
+    subjects: &mut Subjects,
+    students: &mut Students,
+    plan: &[(&str, passes::Pass)],
+    seed: u64,
+    destination: &output::Output,
+) {
+    println!("seed {}", seed);
+    engine::run(subjects, students, plan, seed);
+    if plan.iter().any(|(name, _)| *name == "PH141") {
+        passes::print_without_physics(subjects, students);
+    }
 
-        println!(
-            "Assigned {}/{} students to PH151",
-            subjects["PH151"].values().map(|d| d.assigned).sum::<i32>(),
-            to_be,
-        );
+    let mut state = State::new(subjects, students);
+    state.metadata.seed = Some(seed);
+    state.metadata.pass = plan.last().map(|(name, _)| name.to_string());
+    state.save(state::PATH);
+    write_outputs(subjects, students, destination);
+}
 
-        for (_, subject) in subjects["PH151"].iter().collect::<BTreeMap<_, _>>().iter() {
-            println!("{} {}", subject.division, subject.assigned);
-        }
+/// Promotes waitlisted students into free seats, logs `moves` and the promotions, and saves the
+/// state and every output.
+fn publish(
+    subjects: &mut Subjects,
+    students: &mut Students,
+    moves: &[incremental::Move],
+    reason: &str,
+    destination: &output::Output,
+) {
+    let promotions = waitlist::promote(subjects, students);
+    for m in moves {
+        println!("{}", m);
+    }
+    for m in &promotions {
+        println!("{} (waitlist)", m);
     }
+    waitlist::record(moves, reason);
+    waitlist::record(&promotions, "대기 승급");
 
-    let mut students = students.drain().map(|(_, s)| s).collect::<Vec<_>>();
-    students.sort_by_key(|s| s.id.clone());
-    Student::print_csv(&students);
+    State::new(subjects, students).save(state::PATH);
+    write_outputs(subjects, students, destination);
 }
 
-fn assign(
-    student: &mut Student,
-    subject_id: &str,
-    division_id: &str,
-    subjects: &mut HashMap<String, HashMap<String, Division>>,
-) {
-    let division = &subjects[subject_id][division_id];
-    assert!(student
-        .subjects(subjects)
-        .iter()
-        .all(|d| d.is_disjoint(division)));
-    assert!(
-        division.assigned < division.quota,
-        "{} {}",
-        subject_id,
-        division_id
-    );
-    assert!(
-        matches!(student.subjects[&division.id], Status::Undefined),
-        "s {:?}",
-        student
-    );
+fn write_outputs(subjects: &Subjects, students: &Students, destination: &output::Output) {
+    report::write_division_stats(subjects, students);
+    instructors::write_load(subjects);
+    roster::write_rosters(subjects, students);
+    timetable::write_timetables(subjects, students);
+    workbook::write_workbook(subjects, students);
+    if let Some(semester) = calendar::Semester::read_csv() {
+        calendar::write_calendars(subjects, students, &semester);
+    }
 
-    subjects
-        .get_mut(subject_id)
-        .unwrap()
-        .get_mut(division_id)
-        .unwrap()
-        .assigned += 1;
-    *student.subjects.get_mut(subject_id).unwrap() = Status::Enroll(division_id.to_owned());
+    let mut students = students.values().collect::<Vec<_>>();
+    students.sort_by_key(|s| s.id.clone());
+    output::write_assignment(&students, subjects, destination);
 }
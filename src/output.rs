@@ -0,0 +1,230 @@
+use std::path::Path;
+
+use crate::{
+    ids::SubjectId, input::read_records, report::Table, student::Student, subject::Subjects,
+};
+
+/// Where the assignment goes unless `--output` says otherwise.
+pub const PATH: &str = "./output.csv";
+pub const DELIMITER: u8 = b',';
+/// Optional column layout of `PATH`, with rows of `열` (header) and `항목` (field).
+const PATH_SCHEMA: &str = "./data/csv/output_schema.csv";
+
+/// Where the assignment is written and how its fields are separated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Output {
+    pub path: String,
+    pub delimiter: u8,
+}
+
+/// Parses a delimiter given as a single ASCII character, or `tab` (or `\t`).
+pub fn parse_delimiter(s: &str) -> Option<u8> {
+    match s {
+        "tab" | "\\t" => Some(b'\t'),
+        _ if s.len() == 1 && s.is_ascii() => Some(s.as_bytes()[0]),
+        _ => None,
+    }
+}
+
+/// What a column of the output holds.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Field {
+    Id,
+    Name,
+    Gender,
+    Nationality,
+    HighSchoolCategory,
+    HighSchool,
+    /// The status of the student in the subject.
+    Subject(SubjectId),
+}
+
+impl Field {
+    /// Parses `id`, `name`, `gender`, `nationality`, `high_school_category`, `high_school` or a
+    /// subject id.
+    pub fn parse(s: &str) -> Field {
+        match s.trim() {
+            "id" => Field::Id,
+            "name" => Field::Name,
+            "gender" => Field::Gender,
+            "nationality" => Field::Nationality,
+            "high_school_category" => Field::HighSchoolCategory,
+            "high_school" => Field::HighSchool,
+            other => match SubjectId::parse(other) {
+                Some(subject_id) => Field::Subject(subject_id),
+                None => panic!("unknown output field {:?}", other),
+            },
+        }
+    }
+
+    fn value(&self, student: &Student, subjects: &Subjects) -> String {
+        match self {
+            Field::Id => student.id.clone(),
+            Field::Name => student.name.clone(),
+            Field::Gender => student.gender.clone(),
+            Field::Nationality => student.nationality.clone(),
+            Field::HighSchoolCategory => student.high_school_category.clone(),
+            Field::HighSchool => student.high_school.clone(),
+            Field::Subject(subject_id) => student.subjects[*subject_id].name(subjects),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Column {
+    pub header: String,
+    pub field: Field,
+}
+
+/// The columns of the output, in order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Schema(pub Vec<Column>);
+
+impl Default for Schema {
+    /// The layout handed to the registrar.
+    fn default() -> Self {
+        let columns = [
+            ("학번", Field::Id),
+            ("이름", Field::Name),
+            ("성별", Field::Gender),
+            ("국적", Field::Nationality),
+            ("고교구분", Field::HighSchoolCategory),
+            ("출신고교", Field::HighSchool),
+        ];
+        let subjects = [
+            ("프밍기", "CS101"),
+            ("일생", "BS120"),
+            ("일화", "CH101"),
+            ("일물", "PH141"),
+            ("고급물리", "PH161"),
+            ("실험물리", "PH171"),
+            ("미적", "MAS101"),
+            ("일화실", "CH102"),
+            ("일물실", "PH151"),
+            ("010", "HSS010"),
+            ("011", "HSS011"),
+            ("023", "HSS023"),
+            ("022", "HSS022"),
+            ("025", "HSS025"),
+            ("024", "HSS024"),
+        ];
+
+        Schema(
+            columns
+                .iter()
+                .cloned()
+                .chain(
+                    subjects
+                        .iter()
+                        .map(|&(header, id)| (header, Field::Subject(SubjectId::of(id)))),
+                )
+                .map(|(header, field)| Column {
+                    header: header.to_owned(),
+                    field,
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Schema {
+    /// Reads the schema from `PATH_SCHEMA`, falling back to the default layout.
+    pub fn read() -> Schema {
+        if !Path::new(PATH_SCHEMA).exists() {
+            return Schema::default();
+        }
+
+        let (header, records) = read_records(PATH_SCHEMA, "schema", None);
+        let position = |name| {
+            header
+                .iter()
+                .position(|h| h.trim() == name)
+                .unwrap_or_else(|| panic!("{} has no {} column", PATH_SCHEMA, name))
+        };
+        let (header_at, field_at) = (position("열"), position("항목"));
+
+        Schema(
+            records
+                .iter()
+                .map(|r| Column {
+                    header: r[header_at].trim().to_owned(),
+                    field: Field::parse(&r[field_at]),
+                })
+                .collect(),
+        )
+    }
+
+    pub fn table(&self, students: &[&Student], subjects: &Subjects) -> Table {
+        Table {
+            header: self.0.iter().map(|c| c.header.clone()).collect(),
+            rows: students
+                .iter()
+                .map(|student| {
+                    self.0
+                        .iter()
+                        .map(|c| c.field.value(student, subjects))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Writes the assignment to `output` with the schema from `Schema::read`.
+pub fn write_assignment(students: &[&Student], subjects: &Subjects, output: &Output) {
+    Schema::read()
+        .table(students, subjects)
+        .write_delimited(&output.path, output.delimiter);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        student::{Status, Statuses},
+        subject::Division,
+    };
+
+    #[test]
+    fn table_quoting() {
+        let subjects = Subjects::new(vec![Division {
+            id: "CS101".to_owned(),
+            division: "A".to_owned(),
+            assigned: 1,
+            quota: 40,
+            hour: Vec::new(),
+            ..Default::default()
+        }]);
+        let mut statuses = Statuses::new(Status::No);
+        statuses[SubjectId::of("CS101")] =
+            Status::Enroll(subjects.division(SubjectId::of("CS101"), "A"));
+        let student = Student {
+            id: "20210001".to_owned(),
+            name: "Kim, \"Gaya\"".to_owned(),
+            gender: "여".to_owned(),
+            nationality: "대한민국".to_owned(),
+            subjects: statuses,
+            ..Default::default()
+        };
+        let schema = Schema(vec![
+            Column {
+                header: "이름".to_owned(),
+                field: Field::parse("name"),
+            },
+            Column {
+                header: "프밍기".to_owned(),
+                field: Field::parse("CS101"),
+            },
+        ]);
+
+        let path = std::env::temp_dir().join("saenaegi_table_quoting.csv");
+        let path = path.to_str().unwrap();
+        schema
+            .table(&[&student], &subjects)
+            .write_delimited(path, b';');
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "이름;프밍기\n\"Kim, \"\"Gaya\"\"\";A\n"
+        );
+    }
+}
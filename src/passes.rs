@@ -0,0 +1,443 @@
+use rand::{prelude::*, rngs::StdRng};
+
+use crate::{
+    ids::{DivisionId, SubjectId},
+    student::{Status, Student, Students},
+    subject::{Division, Subjects},
+};
+
+pub type Pass = fn(&mut Subjects, &mut Students, &mut StdRng);
+
+/// The passes of a full run, in order.
+pub const PASSES: &[(&str, Pass)] = &[
+    ("HSS022", hss022),
+    ("CS101", cs101),
+    ("MAS101-CH101", mas101_ch101),
+    ("PH141", ph141),
+    ("HSS010", hss010),
+    ("CH102", ch102),
+    ("PH151", ph151),
+];
+
+/// Counts the divisions given in the input towards their quota.
+pub fn prepare(subjects: &mut Subjects, students: &Students) {
+    for (_, division) in subjects.iter() {
+        assert_eq!(division.assigned, 0);
+    }
+
+    // 외국인, 영어 * 4, 고물, 실물, 일생 배정 정원 반영 및 수정
+    for student in students.values() {
+        let assigned_subjects: Vec<&Division> = student.subjects(subjects);
+
+        for i in 0..assigned_subjects.len() {
+            for j in i + 1..assigned_subjects.len() {
+                assert!(assigned_subjects[i].is_disjoint(assigned_subjects[j]));
+            }
+        }
+
+        for (_, division) in student.subjects.enrolled() {
+            subjects[division].assigned += 1;
+        }
+    }
+}
+
+fn hss022(subjects: &mut Subjects, students: &mut Students, rng: &mut StdRng) {
+    let hss022 = SubjectId::of("HSS022");
+    let (division_a, division_c) = (
+        subjects.division(hss022, "A"),
+        subjects.division(hss022, "C"),
+    );
+    subjects[division_a].quota = 25;
+    subjects[division_c].quota = 25;
+
+    let chosen: Vec<_> = Division::read_hss022_students()
+        .choose_multiple(rng, 25)
+        .collect();
+
+    for &name in Division::read_hss022_students() {
+        let student = students
+            .find(name)
+            .unwrap_or_else(|| panic!("no student {}", name));
+        assign(
+            &mut students[student],
+            if chosen.contains(&&name) {
+                division_c
+            } else {
+                division_a
+            },
+            subjects,
+        );
+    }
+}
+
+fn cs101(subjects: &mut Subjects, students: &mut Students, rng: &mut StdRng) {
+    let cs101 = SubjectId::of("CS101");
+    let mut cs101_students = students
+        .values_mut()
+        .filter(|student| student.subjects[cs101] == Status::Undefined)
+        .collect::<Vec<_>>();
+    cs101_students.shuffle(rng);
+    let divisions: Vec<DivisionId> = ('A'..='J')
+        .map(|division| subjects.division(cs101, &division.to_string()))
+        .collect();
+
+    let mut count = 1;
+    'next_student: for student in cs101_students {
+        count += 1;
+        for &division in &divisions {
+            if student.fits(division, subjects)
+                && subjects[division].quota > subjects[division].assigned
+            {
+                assign(student, division, subjects);
+                continue 'next_student;
+            }
+        }
+        panic!("망함.. {} {:?}", count, student);
+    }
+}
+
+fn mas101_ch101(subjects: &mut Subjects, students: &mut Students, rng: &mut StdRng) {
+    let (mas101, ch101) = (SubjectId::of("MAS101"), SubjectId::of("CH101"));
+
+    for division_char in 'A'..='P' {
+        if division_char == 'K' || division_char == 'L' {
+            continue;
+        }
+        let division_id = subjects.division(mas101, &division_char.to_string());
+        let division = &subjects[division_id];
+
+        for student in students
+            .values_mut()
+            .filter(|s| {
+                matches!(s.subjects[mas101], Status::Undefined) && s.fits(division_id, subjects)
+            })
+            .choose_multiple(rng, (division.quota - division.assigned) as usize)
+        {
+            assign(student, division_id, subjects);
+
+            let mut candidates: Vec<DivisionId> = match division_char {
+                'A' | 'B' | 'C' | 'D' | 'E' | 'F' | 'G' | 'H' => ["A", "B", "C", "D"],
+                'I' | 'J' | 'M' | 'N' | 'O' | 'P' => ["E", "F", "G", "H"],
+                _ => unreachable!(),
+            }
+            .iter()
+            .map(|d| subjects.division(ch101, d))
+            .collect();
+
+            candidates.retain(|&d| subjects[d].quota > subjects[d].assigned);
+            // println!("{:?}", candidates);
+
+            let chosen = *candidates.choose(rng).unwrap_or_else(|| {
+                let divisions = |subject| {
+                    subjects
+                        .of(subject)
+                        .iter()
+                        .map(|&d| &subjects[d])
+                        .collect::<Vec<_>>()
+                };
+                panic!("{:?} {:?}", divisions(mas101), divisions(ch101))
+            });
+            assign(student, chosen, subjects);
+        }
+    }
+
+    for &subject_id in &[mas101, ch101] {
+        let count = students
+            .values()
+            .filter(|student| matches!(student.subjects[subject_id], Status::Enroll(_)))
+            .count();
+        assert_eq!(count, 728);
+    }
+}
+
+fn ph141(subjects: &mut Subjects, students: &mut Students, rng: &mut StdRng) {
+    let ph141 = SubjectId::of("PH141");
+    let find = |names: &[(&str, &str)]| -> Vec<DivisionId> {
+        names
+            .iter()
+            .filter_map(|&(subject, division)| subjects.find(SubjectId::of(subject), division))
+            .collect()
+    };
+    let nearby = find(&[
+        ("HSS011", "B"),
+        ("CS101", "C"),
+        ("CS101", "D"),
+        ("CS101", "G"),
+        ("CS101", "H"),
+    ]);
+    let other_physics = find(&[("PH161", "고급물"), ("PH171", "체감형물")]);
+
+    for student in students.values_mut().filter(|student| {
+        nearby.iter().any(|&d| student.is_taking(d))
+            && !other_physics.iter().any(|&d| student.is_taking(d))
+    }) {
+        let division = ["A", "B", "C"].choose(rng).unwrap();
+        assign(student, subjects.division(ph141, division), subjects);
+    }
+
+    let bs120 = SubjectId::of("BS120");
+    for student in students.values_mut().filter(|student| {
+        matches!(student.subjects[ph141], Status::Undefined)
+            && matches!(student.subjects[bs120], Status::Enroll(_))
+    }) {
+        let mut division = subjects.division(ph141, ["D", "E", "F", "G"].choose(rng).unwrap());
+
+        while subjects[division].assigned >= subjects[division].quota {
+            division = subjects.division(ph141, ["D", "E", "F", "G"].choose(rng).unwrap());
+        }
+
+        assign(student, division, subjects);
+    }
+
+    for &division in &["A", "B", "C"] {
+        let division = subjects.division(ph141, division);
+        for student in students
+            .values_mut()
+            .filter(|student| matches!(student.subjects[ph141], Status::Undefined))
+            .choose_multiple(
+                rng,
+                (subjects[division].quota - subjects[division].assigned) as usize,
+            )
+        {
+            assign(student, division, subjects);
+        }
+    }
+
+    for &division in &["A", "B", "C", "D", "E", "F", "G"] {
+        let division = subjects.division(ph141, division);
+        for student in students
+            .values_mut()
+            .filter(|student| {
+                matches!(student.subjects[ph141], Status::Undefined)
+                    && student.fits(division, subjects)
+            })
+            .choose_multiple(
+                rng,
+                (subjects[division].quota - subjects[division].assigned) as usize,
+            )
+        {
+            assign(student, division, subjects);
+        }
+    }
+}
+
+/// Prints the students `ph141` left without any physics. Kept out of the pass so that runs in
+/// a search stay quiet.
+pub fn print_without_physics(subjects: &Subjects, students: &Students) {
+    let (ph141, ph161, ph171) = (
+        SubjectId::of("PH141"),
+        SubjectId::of("PH161"),
+        SubjectId::of("PH171"),
+    );
+    for s in students.values().filter(|student| {
+        !(matches!(student.subjects[ph141], Status::Enroll(_))
+            || matches!(student.subjects[ph161], Status::Enroll(_))
+            || matches!(student.subjects[ph171], Status::Enroll(_)))
+    }) {
+        println!(
+            "{:?}",
+            s.subjects
+                .iter()
+                .map(|(subject, status)| format!("{} {}", subject, status.name(subjects)))
+                .collect::<Vec<_>>()
+        );
+    }
+}
+
+fn hss010(subjects: &mut Subjects, students: &mut Students, rng: &mut StdRng) {
+    let (hss010, cs101) = (SubjectId::of("HSS010"), SubjectId::of("CS101"));
+    let (cs101_i, cs101_j) = (subjects.division(cs101, "I"), subjects.division(cs101, "J"));
+
+    for &division_id in [
+        "A", "D", "B", "E", "C", "F", //
+        "N", "O", "P", //
+        "T", "V", "U", "W", "AB", //
+        "G", "H", "I", "J", //
+        "K", "L", "M", "Y", //
+        "Q", "R", "S", "Z", "AA", //
+    ]
+    .iter()
+    {
+        let division_id = subjects.division(hss010, division_id);
+        let division = &subjects[division_id];
+        for student in students
+            .values_mut()
+            .filter(|s| {
+                matches!(s.subjects[hss010], Status::Undefined) && s.fits(division_id, subjects)
+                    && matches!(s.subjects[cs101], Status::Enroll(_))
+                    && !s.is_taking(cs101_i)
+                    && !s.is_taking(cs101_j)
+            })
+            .choose_multiple(rng, (division.quota - division.assigned) as usize)
+        {
+            assign(student, division_id, subjects);
+        }
+    }
+
+    for &division_id in [
+        "D", "A", "E", "B", "F", "C", //
+        "T", "U", "AB", //
+        "Q", "R", "S", "Z", "AA", //
+        "K", "L", "M", "Y", //
+        "G", "H", "I", "J", //
+        "V", "W", //
+        "N", "O", "P", //
+    ]
+    .iter()
+    {
+        let division_id = subjects.division(hss010, division_id);
+        let division = &subjects[division_id];
+        for student in students
+            .values_mut()
+            .filter(|s| {
+                matches!(s.subjects[hss010], Status::Undefined) && s.fits(division_id, subjects)
+            })
+            .choose_multiple(rng, (division.quota - division.assigned) as usize)
+        {
+            assign(student, division_id, subjects);
+        }
+    }
+}
+
+fn ch102(subjects: &mut Subjects, students: &mut Students, rng: &mut StdRng) {
+    let (ch102, cs101) = (SubjectId::of("CH102"), SubjectId::of("CS101"));
+
+    for &division_id in &[
+        "V", "J", 
+        "W", "K", 
+        "X", "L",
+
+        "D", "G", "P", "S",
+        "E", "H", "Q", "T",
+        "F", "I", "R", "U",
+
+        "M", "A",
+        "N", "B",
+        "O", "C",
+
+        "AA", "Y", "AB", "Z", "AC", //
+    ] {
+        let division_id = subjects.division(ch102, division_id);
+        let division = &subjects[division_id];
+
+        for student in students
+            .values_mut()
+            .filter(|s| {
+                matches!(s.subjects[ch102], Status::Undefined) && s.fits(division_id, subjects)
+                    && s.subjects[cs101] != Status::No
+            })
+            .choose_multiple(rng, (division.quota - division.assigned) as usize)
+        {
+            assign(student, division_id, subjects);
+        }
+    }
+
+    for &division_id in &[
+        "M", "V", "J", "A",
+        "N", "W", "K", "B",
+        "O", "X", "L", "C",
+
+        "D", "G", "P", "S",
+        "E", "H", "Q", "T",
+        "F", "I", "R", "U",
+
+        "AA", "Y", "AB", "Z", "AC", //
+    ] {
+        let division_id = subjects.division(ch102, division_id);
+        let division = &subjects[division_id];
+
+        for student in students
+            .values_mut()
+            .filter(|s| {
+                matches!(s.subjects[ch102], Status::Undefined) && s.fits(division_id, subjects)
+            })
+            .choose_multiple(rng, (division.quota - division.assigned) as usize)
+        {
+            assign(student, division_id, subjects);
+        }
+    }
+}
+
+fn ph151(subjects: &mut Subjects, students: &mut Students, rng: &mut StdRng) {
+    let (ph151, cs101) = (SubjectId::of("PH151"), SubjectId::of("CS101"));
+
+    for &division_id in &[
+        "K", "L", "M", //
+        "N", "O", //
+        "A", "B", //
+        "F", "G", //
+        "P", "Q", //
+        "C", "D", "E", //
+        "T", "U", "V", //
+        "W", "X", "Y", //
+        "H", "I", "J", //
+        "R", "S", //
+    ] {
+        let division_id = subjects.division(ph151, division_id);
+        let division = &subjects[division_id];
+
+        for student in students
+            .values_mut()
+            .filter(|s| {
+                matches!(s.subjects[ph151], Status::Undefined) && s.fits(division_id, subjects)
+                    && s.subjects[cs101] == Status::No
+            })
+            .choose_multiple(rng, (division.quota - division.assigned) as usize)
+        {
+            assign(student, division_id, subjects);
+        }
+    }
+    for &division_id in &[
+        "K", "L", "M", //
+        "N", "O", //
+        "C", "D", "E", //
+        "A", "B", //
+        "H", "I", "J", //
+        "R", "S", //
+        "T", "U", "V", //
+        "W", "X", "Y", //
+        "F", "G", //
+        "P", "Q", //
+    ] {
+        let division_id = subjects.division(ph151, division_id);
+        let division = &subjects[division_id];
+
+        for student in students
+            .values_mut()
+            .filter(|s| {
+                matches!(s.subjects[ph151], Status::Undefined) && s.fits(division_id, subjects)
+            })
+            .choose_multiple(rng, (division.quota - division.assigned) as usize)
+        {
+            assign(student, division_id, subjects);
+        }
+    }
+}
+
+pub fn assign(student: &mut Student, division_id: DivisionId, subjects: &mut Subjects) {
+    let division = &subjects[division_id];
+    assert!(student.fits(division_id, subjects));
+    assert!(
+        division.assigned < division.quota,
+        "{} {}",
+        division.subject,
+        division.division
+    );
+    assert!(
+        matches!(student.subjects[division.subject], Status::Undefined),
+        "s {:?}",
+        student
+    );
+
+    student.subjects[division.subject] = Status::Enroll(division_id);
+    subjects[division_id].assigned += 1;
+    student.occupancy.clear();
+}
+
+pub fn unassign(student: &mut Student, subject_id: SubjectId, subjects: &mut Subjects) {
+    if let Status::Enroll(division_id) = student.subjects[subject_id] {
+        subjects[division_id].assigned -= 1;
+    }
+    student.subjects[subject_id] = Status::Undefined;
+    student.occupancy.clear();
+}
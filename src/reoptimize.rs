@@ -0,0 +1,559 @@
+use std::collections::VecDeque;
+
+use serde::Deserialize;
+
+use crate::{
+    ids::{DivisionId, StudentId, SubjectId},
+    incremental::Move,
+    input::read_records,
+    passes::{assign, unassign},
+    student::{Status, Statuses, Student, Students},
+    subject::{parse_intervals, Division, Subjects},
+};
+
+/// A new quota or lecture time for a published division.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Edit {
+    pub division: DivisionId,
+    pub quota: Option<i32>,
+    pub hour: Option<Vec<[i32; 2]>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    #[serde(rename = "과목번호")]
+    subject: String,
+    #[serde(rename = "분반")]
+    division: String,
+    #[serde(rename = "정원", default)]
+    quota: String,
+    #[serde(rename = "강의시간", default)]
+    hour: String,
+}
+
+/// Reads rows of (`과목번호`, `분반`, `정원`, `강의시간`), leaving `정원` or `강의시간` empty to
+/// keep it as it is.
+pub fn read_edits(path: &str, subjects: &Subjects) -> Vec<Edit> {
+    let (header, records) = read_records(path, "edits", None);
+    records
+        .iter()
+        .map(|record| {
+            let row: Row = record.deserialize(Some(&header)).unwrap();
+            let quota = row.quota.trim();
+            let hour = row.hour.trim();
+            Edit {
+                division: subjects
+                    .lookup(&row.subject, &row.division)
+                    .unwrap_or_else(|| {
+                        panic!("no division {} {}", row.subject.trim(), row.division.trim())
+                    }),
+                quota: (!quota.is_empty()).then(|| {
+                    quota
+                        .parse()
+                        .unwrap_or_else(|_| panic!("invalid quota {:?}", quota))
+                }),
+                hour: (!hour.is_empty())
+                    .then(|| parse_intervals(hour).unwrap_or_else(|e| panic!("{}", e))),
+            }
+        })
+        .collect()
+}
+
+/// Applies `edits` to the catalog, then looks for a valid assignment with as few (student,
+/// subject) placements changed from the current one as it can find, after placing as many
+/// students as possible.
+///
+/// A student whose divisions now clash first gives up one of them, as `clashing` picks. Then
+/// the divisions of each subject in turn are chosen by a min-cost flow, with the student's
+/// divisions of other subjects fixed: every change costs one, and leaving a student unplaced
+/// costs more than all the changes together. This is minimal for each subject given the
+/// others; the subjects are gone over again as long as that still improves the whole.
+///
+/// Returns the placements that changed, the (student, subject) pairs left unplaced, and the
+/// divisions still over quota because too many of their students are pinned there.
+pub fn reoptimize(
+    subjects: &mut Subjects,
+    students: &mut Students,
+    edits: Vec<Edit>,
+) -> (Vec<Move>, Vec<(StudentId, SubjectId)>, Vec<DivisionId>) {
+    for edit in edits {
+        let division = &mut subjects[edit.division];
+        if let Some(quota) = edit.quota {
+            division.quota = quota;
+        }
+        if let Some(hour) = edit.hour {
+            division.hour = hour;
+            division.update_masks();
+        }
+    }
+    // the edited divisions may be in any student's cached occupancy
+    for student in students.values() {
+        student.occupancy.clear();
+    }
+
+    let mut baseline: Vec<(StudentId, Statuses)> = students
+        .iter()
+        .map(|(id, student)| (id, student.subjects.clone()))
+        .collect();
+    baseline.sort_by(|a, b| students[a.0].id.cmp(&students[b.0].id));
+
+    for &(id, _) in &baseline {
+        while let Some(subject_id) = clashing(subjects, &students[id]) {
+            unassign(&mut students[id], subject_id, subjects);
+        }
+    }
+
+    // (unplaced, changed), which every subject's flow can only lower
+    let cost = |students: &Students| {
+        let statuses = baseline.iter().flat_map(|(id, before)| {
+            let now = &students[*id].subjects;
+            SubjectId::all().map(move |subject_id| (before[subject_id], now[subject_id]))
+        });
+        statuses.fold((0, 0), |(unplaced, changed), (before, now)| {
+            (
+                unplaced + (now == Status::Undefined) as usize,
+                changed + (before.division() != now.division()) as usize,
+            )
+        })
+    };
+    let mut last = None;
+    while last.is_none_or(|last| cost(students) < last) {
+        last = Some(cost(students));
+        for subject_id in SubjectId::all() {
+            assign_subject(subjects, students, &baseline, subject_id);
+        }
+    }
+
+    let mut unplaced = Vec::new();
+    let mut moves = Vec::new();
+    for (id, before) in &baseline {
+        let student = &students[*id];
+        for subject_id in SubjectId::all() {
+            let from = before[subject_id].division();
+            let to = student.subjects[subject_id].division();
+            if from != to {
+                moves.push(Move::new(student, subject_id, from, to, subjects));
+            }
+            if student.subjects[subject_id] == Status::Undefined {
+                unplaced.push((*id, subject_id));
+            }
+        }
+    }
+    let overfull = subjects
+        .iter()
+        .filter(|(_, d)| d.assigned > d.quota)
+        .map(|(division_id, _)| division_id)
+        .collect();
+    (moves, unplaced, overfull)
+}
+
+/// Chooses the divisions of `subject_id` for every student taking it or left undefined in it,
+/// except those pinned to theirs, by a min-cost flow from the students to the divisions that
+/// fit the rest of their timetable. A student keeping their division from `baseline` costs
+/// nothing, going to any other costs one, and staying unplaced costs more than every student
+/// changing.
+fn assign_subject(
+    subjects: &mut Subjects,
+    students: &mut Students,
+    baseline: &[(StudentId, Statuses)],
+    subject_id: SubjectId,
+) {
+    let divisions = subjects.of(subject_id).to_vec();
+    if divisions.is_empty() {
+        return;
+    }
+    // pinned students keep their seats, and take them first
+    let mut seats: Vec<i64> = divisions
+        .iter()
+        .map(|&d| subjects[d].quota.max(0) as i64)
+        .collect();
+    let mut members = Vec::new();
+    for (id, before) in baseline {
+        let student = &students[*id];
+        match student.subjects[subject_id] {
+            Status::No => {}
+            Status::Enroll(d) if student.pinned.contains(&subject_id) => {
+                let i = divisions.iter().position(|&other| other == d).unwrap();
+                seats[i] = (seats[i] - 1).max(0);
+            }
+            _ => members.push((*id, before[subject_id].division())),
+        }
+    }
+    if members.is_empty() {
+        return;
+    }
+
+    // source, sink, then members, then divisions
+    let (source, sink) = (0, 1);
+    let member = |i: usize| 2 + i;
+    let division = |i: usize| 2 + members.len() + i;
+    let mut flow = Flow::new(2 + members.len() + divisions.len());
+    let unplaced = members.len() as i64 + 1;
+    let mut kept = Vec::new();
+    for (i, &(id, before)) in members.iter().enumerate() {
+        flow.add(source, member(i), 1, 0);
+        flow.add(member(i), sink, 1, unplaced);
+        for (j, &d) in divisions.iter().enumerate() {
+            if students[id].fits(d, subjects) {
+                let edge = flow.add(member(i), division(j), 1, (before != Some(d)) as i64);
+                if before == Some(d) && students[id].subjects[subject_id] == Status::Enroll(d) {
+                    kept.push((i, j, edge));
+                }
+            }
+        }
+    }
+    let to_sink: Vec<usize> = seats
+        .iter()
+        .enumerate()
+        .map(|(j, &seats)| flow.add(division(j), sink, seats, 0))
+        .collect();
+
+    // students still in their division from `baseline` stay there for free, which no flow of
+    // as many students beats; the rest are placed along the cheapest paths
+    for (i, j, edge) in kept {
+        if flow.residual(to_sink[j]) > 0 {
+            flow.push(flow.source_edge(member(i)), 1);
+            flow.push(edge, 1);
+            flow.push(to_sink[j], 1);
+        }
+    }
+    while flow.augment(source, sink) {}
+
+    let chosen: Vec<Option<DivisionId>> = (0..members.len())
+        .map(|i| {
+            (0..divisions.len())
+                .find(|&j| flow.carries(member(i), division(j)))
+                .map(|j| divisions[j])
+        })
+        .collect();
+    // everyone leaves before anyone enters, so that no division is ever over its quota
+    for (&(id, _), &to) in members.iter().zip(&chosen) {
+        if students[id].subjects[subject_id].division() != to {
+            unassign(&mut students[id], subject_id, subjects);
+        }
+    }
+    for (&(id, _), &to) in members.iter().zip(&chosen) {
+        if let Some(to) = to {
+            if students[id].subjects[subject_id] == Status::Undefined {
+                assign(&mut students[id], to, subjects);
+            }
+        }
+    }
+}
+
+/// A flow network for `assign_subject`, with a reverse edge right after every edge.
+struct Flow {
+    edges: Vec<FlowEdge>,
+    out: Vec<Vec<usize>>,
+}
+
+struct FlowEdge {
+    from: usize,
+    to: usize,
+    capacity: i64,
+    cost: i64,
+}
+
+impl Flow {
+    fn new(nodes: usize) -> Self {
+        Flow {
+            edges: Vec::new(),
+            out: vec![Vec::new(); nodes],
+        }
+    }
+
+    /// Adds an edge and returns its index.
+    fn add(&mut self, from: usize, to: usize, capacity: i64, cost: i64) -> usize {
+        let edge = self.edges.len();
+        self.out[from].push(edge);
+        self.out[to].push(edge + 1);
+        self.edges.push(FlowEdge {
+            from,
+            to,
+            capacity,
+            cost,
+        });
+        self.edges.push(FlowEdge {
+            from: to,
+            to: from,
+            capacity: 0,
+            cost: -cost,
+        });
+        edge
+    }
+
+    fn residual(&self, edge: usize) -> i64 {
+        self.edges[edge].capacity
+    }
+
+    fn push(&mut self, edge: usize, amount: i64) {
+        self.edges[edge].capacity -= amount;
+        self.edges[edge ^ 1].capacity += amount;
+    }
+
+    /// The edge into `node` added first, from the source.
+    fn source_edge(&self, node: usize) -> usize {
+        self.out[node][0] ^ 1
+    }
+
+    /// Whether a unit flows along an edge from `from` to `to`.
+    fn carries(&self, from: usize, to: usize) -> bool {
+        self.out[from]
+            .iter()
+            .any(|&e| e % 2 == 0 && self.edges[e].to == to && self.edges[e ^ 1].capacity > 0)
+    }
+
+    /// Sends a unit from `source` to `sink` along the cheapest path with room, by Bellman-Ford
+    /// since reverse edges cost less than nothing. Returns whether there was one.
+    fn augment(&mut self, source: usize, sink: usize) -> bool {
+        let mut distance = vec![i64::MAX; self.out.len()];
+        let mut via = vec![None; self.out.len()];
+        let mut queued = vec![false; self.out.len()];
+        let mut queue = VecDeque::from(vec![source]);
+        distance[source] = 0;
+        while let Some(node) = queue.pop_front() {
+            queued[node] = false;
+            for &e in &self.out[node] {
+                let edge = &self.edges[e];
+                if edge.capacity > 0 && distance[node] + edge.cost < distance[edge.to] {
+                    distance[edge.to] = distance[node] + edge.cost;
+                    via[edge.to] = Some(e);
+                    if !queued[edge.to] {
+                        queued[edge.to] = true;
+                        queue.push_back(edge.to);
+                    }
+                }
+            }
+        }
+        if via[sink].is_none() {
+            return false;
+        }
+
+        let mut node = sink;
+        while let Some(e) = via[node] {
+            self.push(e, 1);
+            node = self.edges[e].from;
+        }
+        true
+    }
+}
+
+/// The subject a student should give up to resolve a clash between their divisions, if they
+/// have one. Pinned subjects are kept when possible, and subjects with another division to go
+/// to are given up first, then those in an overfull division.
+fn clashing(subjects: &Subjects, student: &Student) -> Option<SubjectId> {
+    let enrolled: Vec<(SubjectId, &Division)> = student
+        .subjects
+        .enrolled()
+        .map(|(subject_id, d)| (subject_id, &subjects[d]))
+        .collect();
+
+    enrolled
+        .iter()
+        .map(|&(subject_id, division)| {
+            let clashes = enrolled
+                .iter()
+                .filter(|(other, d)| *other != subject_id && !d.is_disjoint(division))
+                .count();
+            (subject_id, division, clashes)
+        })
+        .filter(|&(_, _, clashes)| clashes > 0)
+        .min_by_key(|&(subject_id, division, clashes)| {
+            (
+                student.pinned.contains(&subject_id),
+                !has_alternative(subjects, student, subject_id),
+                division.assigned <= division.quota,
+                usize::MAX - clashes,
+            )
+        })
+        .map(|(subject_id, _, _)| subject_id)
+}
+
+/// Whether another division of `subject_id` has a free seat that fits the student's other
+/// divisions.
+fn has_alternative(subjects: &Subjects, student: &Student, subject_id: SubjectId) -> bool {
+    subjects.of(subject_id).iter().any(|&division_id| {
+        let division = &subjects[division_id];
+        !student.is_taking(division_id)
+            && division.assigned < division.quota
+            && student.fits(division_id, subjects)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn division(id: &str, division: &str, assigned: i32, quota: i32, hour: [i32; 2]) -> Division {
+        Division {
+            id: id.to_owned(),
+            division: division.to_owned(),
+            assigned,
+            quota,
+            hour: vec![hour],
+            ..Default::default()
+        }
+    }
+
+    fn student(subjects: &Subjects, id: &str, cs101: &str, mas101: &str) -> Student {
+        let mut statuses = Statuses::new(Status::No);
+        for (subject, division) in [("CS101", cs101), ("MAS101", mas101)] {
+            let subject = SubjectId::of(subject);
+            statuses[subject] = Status::Enroll(subjects.division(subject, division));
+        }
+        Student {
+            id: id.to_owned(),
+            subjects: statuses,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reoptimize_moves_few() {
+        let mut subjects = Subjects::new(vec![
+            division("CS101", "A", 3, 3, [540, 600]),
+            division("CS101", "B", 0, 3, [600, 660]),
+            division("MAS101", "A", 2, 3, [660, 720]),
+            division("MAS101", "B", 1, 3, [720, 780]),
+        ]);
+        let (cs101, mas101) = (SubjectId::of("CS101"), SubjectId::of("MAS101"));
+        let d = |subject, division| subjects.division(subject, division);
+        let (cs101_a, cs101_b) = (d(cs101, "A"), d(cs101, "B"));
+        let (mas101_a, mas101_b) = (d(mas101, "A"), d(mas101, "B"));
+
+        // CS101 A loses a seat and MAS101 A moves onto it
+        let mut students = Students::new(vec![
+            student(&subjects, "1", "A", "A"),
+            student(&subjects, "2", "A", "A"),
+            student(&subjects, "3", "A", "B"),
+        ]);
+        let first = students.find("1").unwrap();
+        students[first].pinned.insert(cs101);
+
+        let edits = vec![
+            Edit {
+                division: cs101_a,
+                quota: Some(2),
+                hour: None,
+            },
+            Edit {
+                division: mas101_a,
+                quota: None,
+                hour: Some(vec![[540, 600]]),
+            },
+        ];
+        let (moves, unplaced, overfull) = reoptimize(&mut subjects, &mut students, edits);
+
+        assert!(unplaced.is_empty());
+        assert!(overfull.is_empty());
+        // 1 is pinned to CS101 A, so gives up MAS101 A; 2 gives up the seat CS101 A lost
+        assert_eq!(moves.len(), 2);
+        let student = |id| &students[students.find(id).unwrap()];
+        assert!(student("1").is_taking(mas101_b));
+        assert!(student("2").is_taking(cs101_b));
+        assert!(student("2").is_taking(mas101_a));
+        assert!(student("3").is_taking(cs101_a));
+        assert_eq!(subjects[cs101_a].assigned, 2);
+    }
+
+    #[test]
+    fn reoptimize_moves_along_chain() {
+        // CS101 A closes, B and C are full, and only D has a free seat; each student's MAS101
+        // division leaves them two CS101 divisions, so it takes three moves to place everyone
+        let mas101 = |name: &str, hour: Vec<[i32; 2]>| Division {
+            hour,
+            ..division("MAS101", name, 1, 1, [0, 0])
+        };
+        let mut subjects = Subjects::new(vec![
+            division("CS101", "A", 1, 1, [540, 600]),
+            division("CS101", "B", 1, 1, [600, 660]),
+            division("CS101", "C", 1, 1, [660, 720]),
+            division("CS101", "D", 0, 1, [720, 780]),
+            mas101("A", vec![[660, 780]]),
+            mas101("B", vec![[480, 600], [720, 780]]),
+            mas101("C", vec![[480, 660]]),
+        ]);
+        let cs101 = SubjectId::of("CS101");
+        let mut students = Students::new(vec![
+            student(&subjects, "1", "A", "A"),
+            student(&subjects, "2", "B", "B"),
+            student(&subjects, "3", "C", "C"),
+        ]);
+
+        let edits = vec![Edit {
+            division: subjects.division(cs101, "A"),
+            quota: Some(0),
+            hour: None,
+        }];
+        let (moves, unplaced, overfull) = reoptimize(&mut subjects, &mut students, edits);
+
+        assert!(unplaced.is_empty() && overfull.is_empty());
+        assert_eq!(moves.len(), 3);
+        for (name, division) in &[("1", "B"), ("2", "C"), ("3", "D")] {
+            let student = &students[students.find(name).unwrap()];
+            assert!(student.is_taking(subjects.division(cs101, division)));
+        }
+    }
+
+    #[test]
+    fn reoptimize_reports_pinned_overfull() {
+        let mut subjects = Subjects::new(vec![
+            division("CS101", "A", 2, 2, [540, 600]),
+            division("MAS101", "A", 2, 2, [660, 720]),
+        ]);
+        let cs101 = SubjectId::of("CS101");
+        let cs101_a = subjects.division(cs101, "A");
+
+        let mut students = Students::new(vec![
+            student(&subjects, "1", "A", "A"),
+            student(&subjects, "2", "A", "A"),
+        ]);
+        for name in &["1", "2"] {
+            let id = students.find(name).unwrap();
+            students[id].pinned.insert(cs101);
+        }
+
+        let edits = vec![Edit {
+            division: cs101_a,
+            quota: Some(1),
+            hour: None,
+        }];
+        let (moves, unplaced, overfull) = reoptimize(&mut subjects, &mut students, edits);
+
+        assert!(moves.is_empty() && unplaced.is_empty());
+        assert_eq!(overfull, vec![cs101_a]);
+    }
+
+    #[test]
+    fn reoptimize_clears_occupancy() {
+        let mut subjects = Subjects::new(vec![
+            division("CS101", "A", 1, 2, [540, 600]),
+            division("MAS101", "A", 0, 2, [720, 780]),
+        ]);
+        let (cs101, mas101) = (SubjectId::of("CS101"), SubjectId::of("MAS101"));
+        let (cs101_a, mas101_a) = (
+            subjects.division(cs101, "A"),
+            subjects.division(mas101, "A"),
+        );
+        // so that `fits` goes by the masks and the cached occupancy
+        subjects[cs101_a].update_masks();
+        subjects[mas101_a].update_masks();
+
+        let mut student = Student {
+            id: "1".to_owned(),
+            ..Default::default()
+        };
+        student.subjects[cs101] = Status::Enroll(cs101_a);
+        let mut students = Students::new(vec![student]);
+        let id = students.find("1").unwrap();
+        assert!(students[id].fits(mas101_a, &subjects));
+
+        let edits = vec![Edit {
+            division: cs101_a,
+            quota: None,
+            hour: Some(vec![[720, 780]]),
+        }];
+        reoptimize(&mut subjects, &mut students, edits);
+        assert!(!students[id].fits(mas101_a, &subjects));
+    }
+}
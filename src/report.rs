@@ -0,0 +1,197 @@
+use std::{collections::BTreeSet, fs};
+
+use crate::{
+    ids::SubjectId,
+    student::{Status, Student, Students, SUBJECTS},
+    subject::Subjects,
+};
+
+const PATH_CSV: &str = "./report.csv";
+const PATH_MARKDOWN: &str = "./report.md";
+
+/// A plain table of strings, rendered as CSV or Markdown.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Table {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn write_csv(&self, path: &str) {
+        self.write_delimited(path, b',');
+    }
+
+    pub fn write_delimited(&self, path: &str, delimiter: u8) {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_path(path)
+            .unwrap();
+        writer.write_record(&self.header).unwrap();
+        for row in &self.rows {
+            writer.write_record(row).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let line = |cells: &[String]| {
+            let cells: Vec<String> = cells.iter().map(|c| c.replace('|', "\\|")).collect();
+            format!("| {} |\n", cells.join(" | "))
+        };
+
+        let mut buf = line(&self.header);
+        buf.push_str(&line(&vec!["---".to_owned(); self.header.len()]));
+        for row in &self.rows {
+            buf.push_str(&line(row));
+        }
+        buf
+    }
+
+    pub fn to_html(&self, title: &str) -> String {
+        let line = |tag: &str, cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .map(|c| format!("<{0}>{1}</{0}>", tag, escape_html(c)))
+                .collect();
+            format!("<tr>{}</tr>\n", cells.concat())
+        };
+
+        let mut buf = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+             <style>table {{ border-collapse: collapse; }} td, th {{ border: 1px solid #999; \
+             padding: 2px 6px; font-size: 12px; }}</style>\n</head>\n<body>\n<h1>{0}</h1>\n\
+             <table>\n",
+            escape_html(title)
+        );
+        buf.push_str(&line("th", &self.header));
+        for row in &self.rows {
+            buf.push_str(&line("td", row));
+        }
+        buf.push_str("</table>\n</body>\n</html>\n");
+        buf
+    }
+}
+
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Sorts division ids as they appear on the timetable: `A`, ..., `Z`, `AA`, ...
+pub fn division_order(id: &str) -> (usize, &str) {
+    (id.len(), id)
+}
+
+/// One row per division with its fill rate and the make-up of its students.
+pub fn division_stats(subjects: &Subjects, students: &Students) -> Table {
+    let values = |f: fn(&Student) -> &String| -> BTreeSet<&str> {
+        students.values().map(|s| f(s).as_str()).collect()
+    };
+    let genders = values(|s| &s.gender);
+    let nationalities = values(|s| &s.nationality);
+    let categories = values(|s| &s.high_school_category);
+
+    let mut header: Vec<String> = [
+        "과목번호",
+        "과목명",
+        "분반",
+        "영어",
+        "정원",
+        "배정",
+        "충원율",
+    ]
+    .iter()
+    .map(|&s| s.to_owned())
+    .collect();
+    header.extend(genders.iter().map(|g| format!("성별:{}", g)));
+    header.extend(nationalities.iter().map(|n| format!("국적:{}", n)));
+    header.extend(categories.iter().map(|c| format!("고교구분:{}", c)));
+    // enrolled / not taking / undefined
+    header.extend(SUBJECTS.iter().map(|s| format!("{} (수강/-/?)", s)));
+
+    let mut rows = Vec::new();
+    for (division_id, division) in subjects.iter() {
+        let subject_id = division.subject;
+        let members: Vec<&Student> = students
+            .values()
+            .filter(|s| s.is_taking(division_id))
+            .collect();
+
+        let count = |f: fn(&Student) -> &String, value: &str| {
+            members.iter().filter(|s| f(s) == value).count().to_string()
+        };
+
+        let mut row = vec![
+            subject_id.to_string(),
+            division.name.clone(),
+            division.division.clone(),
+            if division.english { "Y" } else { "" }.to_owned(),
+            division.quota.to_string(),
+            division.assigned.to_string(),
+            if division.quota > 0 {
+                format!(
+                    "{:.1}%",
+                    division.assigned as f64 / division.quota as f64 * 100.0
+                )
+            } else {
+                "-".to_owned()
+            },
+        ];
+        row.extend(genders.iter().map(|g| count(|s| &s.gender, g)));
+        row.extend(nationalities.iter().map(|n| count(|s| &s.nationality, n)));
+        row.extend(
+            categories
+                .iter()
+                .map(|c| count(|s| &s.high_school_category, c)),
+        );
+        row.extend(SubjectId::all().map(|other| {
+            if other == subject_id {
+                return String::new();
+            }
+            let mut counts = [0; 3];
+            for student in &members {
+                match student.subjects[other] {
+                    Status::Enroll(_) => counts[0] += 1,
+                    Status::No => counts[1] += 1,
+                    Status::Undefined => counts[2] += 1,
+                }
+            }
+            format!("{}/{}/{}", counts[0], counts[1], counts[2])
+        }));
+        rows.push(row);
+    }
+
+    Table { header, rows }
+}
+
+pub fn write_division_stats(subjects: &Subjects, students: &Students) {
+    let table = division_stats(subjects, students);
+    table.write_csv(PATH_CSV);
+    fs::write(PATH_MARKDOWN, table.to_markdown()).unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn markdown_simple() {
+        let table = Table {
+            header: vec!["분반".to_owned(), "배정".to_owned()],
+            rows: vec![vec!["A|B".to_owned(), "3".to_owned()]],
+        };
+        assert_eq!(
+            table.to_markdown(),
+            "| 분반 | 배정 |\n| --- | --- |\n| A\\|B | 3 |\n"
+        );
+    }
+
+    #[test]
+    fn division_order_simple() {
+        let mut ids = vec!["AA", "B", "A", "Z", "AB"];
+        ids.sort_by_key(|id| division_order(id));
+        assert_eq!(ids, vec!["A", "B", "Z", "AA", "AB"]);
+    }
+}
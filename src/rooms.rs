@@ -0,0 +1,196 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    input::read_records,
+    report::division_order,
+    subject::{Division, Subjects},
+};
+
+/// Rooms and how many students they seat. Optional; without it capacities are not checked.
+const PATH: &str = "./data/csv/rooms.csv";
+/// Sheet to read when `PATH` is an `.xlsx` workbook.
+const SHEET: &str = "강의실";
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    #[serde(rename = "건물")]
+    building: String,
+    #[serde(rename = "강의실")]
+    room: String,
+    #[serde(rename = "수용인원")]
+    capacity: i32,
+}
+
+/// Capacity of every room, by (building, room). Returns `None` if there is no room file.
+pub fn read_capacities() -> Option<HashMap<(String, String), i32>> {
+    if !Path::new(PATH).exists() {
+        return None;
+    }
+
+    let (header, records) = read_records(PATH, SHEET, None);
+    Some(
+        records
+            .iter()
+            .map(|record| {
+                let row: Row = record
+                    .deserialize(Some(&header))
+                    .unwrap_or_else(|e| panic!("{}: {}", PATH, e));
+                (
+                    (row.building.trim().to_owned(), row.room.trim().to_owned()),
+                    row.capacity,
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Something wrong with the rooms divisions are given.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Problem {
+    /// The division's quota does not fit in its room.
+    OverCapacity {
+        division: String,
+        quota: i32,
+        capacity: i32,
+    },
+    /// The division's room is not in the room file.
+    UnknownRoom { division: String, room: String },
+    /// Two divisions meet in the same room at the same time.
+    DoubleBooked {
+        room: String,
+        lhs: String,
+        rhs: String,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::OverCapacity {
+                division,
+                quota,
+                capacity,
+            } => write!(
+                f,
+                "{}: quota {} exceeds room capacity {}",
+                division, quota, capacity
+            ),
+            Problem::UnknownRoom { division, room } => {
+                write!(f, "{}: unknown room {}", division, room)
+            }
+            Problem::DoubleBooked { room, lhs, rhs } => {
+                write!(f, "{} and {} both meet in {}", lhs, rhs, room)
+            }
+        }
+    }
+}
+
+fn name(division: &Division) -> String {
+    format!("{} {}", division.id, division.division)
+}
+
+fn room(division: &Division) -> String {
+    format!("{} {}", division.building, division.room)
+        .trim()
+        .to_owned()
+}
+
+/// Checks every division with a room against `capacities`, if there are any, and against every
+/// other division in the same room.
+pub fn check(
+    subjects: &Subjects,
+    capacities: Option<&HashMap<(String, String), i32>>,
+) -> Vec<Problem> {
+    let mut divisions: Vec<&Division> = subjects
+        .iter()
+        .map(|(_, d)| d)
+        .filter(|d| !d.room.is_empty())
+        .collect();
+    divisions.sort_by(|a, b| {
+        (&a.id, division_order(&a.division)).cmp(&(&b.id, division_order(&b.division)))
+    });
+
+    let mut problems = Vec::new();
+    if let Some(capacities) = capacities {
+        for d in &divisions {
+            let key = (d.building.clone(), d.room.clone());
+            match capacities.get(&key) {
+                Some(&capacity) if d.quota > capacity => problems.push(Problem::OverCapacity {
+                    division: name(d),
+                    quota: d.quota,
+                    capacity,
+                }),
+                Some(_) => {}
+                None => problems.push(Problem::UnknownRoom {
+                    division: name(d),
+                    room: room(d),
+                }),
+            }
+        }
+    }
+
+    for (i, lhs) in divisions.iter().enumerate() {
+        for rhs in &divisions[i + 1..] {
+            if room(lhs) == room(rhs) && !lhs.is_disjoint(rhs) {
+                problems.push(Problem::DoubleBooked {
+                    room: room(lhs),
+                    lhs: name(lhs),
+                    rhs: name(rhs),
+                });
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn division(id: &str, room: &str, quota: i32, hour: [i32; 2]) -> Division {
+        Division {
+            id: id.to_owned(),
+            division: "A".to_owned(),
+            quota,
+            hour: vec![hour],
+            building: "E11".to_owned(),
+            room: room.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn check_simple() {
+        let subjects = Subjects::new(vec![
+            division("CH101", "101", 40, [540, 630]),
+            division("CS101", "101", 30, [600, 690]),
+            division("MAS101", "102", 30, [600, 690]),
+        ]);
+        let capacities: HashMap<(String, String), i32> =
+            vec![(("E11".to_owned(), "101".to_owned()), 35)]
+                .into_iter()
+                .collect();
+
+        assert_eq!(
+            check(&subjects, Some(&capacities)),
+            vec![
+                Problem::OverCapacity {
+                    division: "CH101 A".to_owned(),
+                    quota: 40,
+                    capacity: 35,
+                },
+                Problem::UnknownRoom {
+                    division: "MAS101 A".to_owned(),
+                    room: "E11 102".to_owned(),
+                },
+                Problem::DoubleBooked {
+                    room: "E11 101".to_owned(),
+                    lhs: "CH101 A".to_owned(),
+                    rhs: "CS101 A".to_owned(),
+                },
+            ]
+        );
+    }
+}
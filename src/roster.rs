@@ -0,0 +1,102 @@
+use std::fs;
+
+use crate::{
+    ids::DivisionId,
+    report::Table,
+    student::{Student, Students},
+    subject::Subjects,
+};
+
+const PATH: &str = "./rosters";
+
+/// The students of one division, with the other divisions each of them is enrolled in.
+pub fn roster(subjects: &Subjects, students: &Students, division_id: DivisionId) -> Table {
+    let subject_id = subjects[division_id].subject;
+    let mut members: Vec<&Student> = students
+        .values()
+        .filter(|s| s.is_taking(division_id))
+        .collect();
+    members.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Table {
+        header: ["학번", "이름", "국적", "수강 분반"]
+            .iter()
+            .map(|&s| s.to_owned())
+            .collect(),
+        rows: members
+            .into_iter()
+            .map(|student| {
+                let others: Vec<String> = student
+                    .subjects
+                    .enrolled()
+                    .filter(|&(other, _)| other != subject_id)
+                    .map(|(other, d)| format!("{} {}", other, subjects[d].division))
+                    .collect();
+                vec![
+                    student.id.clone(),
+                    student.name.clone(),
+                    student.nationality.clone(),
+                    others.join(", "),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// Writes `<subject>/<division>.csv` and `<subject>/<division>.html` for every division.
+pub fn write_rosters(subjects: &Subjects, students: &Students) {
+    for (division_id, division) in subjects.iter() {
+        let dir = format!("{}/{}", PATH, division.subject);
+        fs::create_dir_all(&dir).unwrap();
+
+        let name = &division.division;
+        let table = roster(subjects, students, division_id);
+        table.write_csv(&format!("{}/{}.csv", dir, name));
+        fs::write(
+            format!("{}/{}.html", dir, name),
+            table.to_html(&format!(
+                "{} {} {}분반 ({}/{})",
+                division.subject, division.name, name, division.assigned, division.quota
+            )),
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ids::SubjectId, student::Status, subject::Division};
+
+    #[test]
+    fn roster_simple() {
+        let division = |id: &str, division: &str| Division {
+            id: id.to_owned(),
+            division: division.to_owned(),
+            ..Default::default()
+        };
+        let subjects = Subjects::new(vec![division("CS101", "A"), division("MAS101", "B")]);
+        let (cs101, mas101) = (SubjectId::of("CS101"), SubjectId::of("MAS101"));
+        let a = subjects.division(cs101, "A");
+
+        let student = |id: &str| {
+            let mut student = Student {
+                id: id.to_owned(),
+                ..Default::default()
+            };
+            student.subjects[cs101] = Status::Enroll(a);
+            student
+        };
+        let mut first = student("20210002");
+        first.subjects[mas101] = Status::Enroll(subjects.division(mas101, "B"));
+        let mut students = Students::new(vec![first, student("20210003")]);
+        // inserted last, so only the sort puts them first
+        students.insert(student("20210001"));
+
+        let table = roster(&subjects, &students, a);
+        let ids: Vec<&str> = table.rows.iter().map(|r| r[0].as_str()).collect();
+        assert_eq!(ids, vec!["20210001", "20210002", "20210003"]);
+        assert_eq!(table.rows[1][3], "MAS101 B");
+        assert_eq!(table.rows[0][3], "");
+    }
+}
@@ -0,0 +1,354 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    ids::{DivisionId, StudentId, SubjectId},
+    input::read_records,
+    report::Table,
+    student::{Status, Student, Students},
+    subject::Subjects,
+    waitlist,
+};
+
+/// Weight of every metric. Optional; metrics it leaves out keep their default weight.
+const PATH_WEIGHTS: &str = "./data/csv/weights.csv";
+/// Sheet to read when `PATH_WEIGHTS` is an `.xlsx` workbook.
+const SHEET_WEIGHTS: &str = "가중치";
+/// Divisions students asked for, as read by `waitlist::read_preferences`. Optional.
+const PATH_PREFERENCES: &str = "./data/csv/preferences.csv";
+
+const DAY: i32 = 24 * 60;
+
+/// How far an assignment is from ideal, by metric. Every metric is a penalty: lower is better.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// Subjects left undefined, counted over every student.
+    pub unassigned: f64,
+    /// Variance of the fill rates of the divisions of a subject, summed over subjects.
+    pub fill_variance: f64,
+    /// Total variation distance between the make-up of a division and that of everyone taking
+    /// its subject, by gender, nationality and high school category, averaged over divisions.
+    pub demographics: f64,
+    /// Preferred divisions students are not in.
+    pub preferences: f64,
+    /// Hours between classes on the same day, averaged over students.
+    pub gaps: f64,
+}
+
+/// How much each metric counts towards the score.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weights(Metrics);
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights(Metrics {
+            unassigned: 1000.0,
+            fill_variance: 100.0,
+            demographics: 100.0,
+            preferences: 1.0,
+            gaps: 1.0,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    #[serde(rename = "항목")]
+    metric: String,
+    #[serde(rename = "가중치")]
+    weight: f64,
+}
+
+/// Names of the metrics in reports and in `PATH_WEIGHTS`.
+const NAMES: [&str; 5] = ["미배정", "충원율 분산", "구성 편차", "희망 미충족", "공강"];
+
+impl Metrics {
+    pub fn measure(
+        subjects: &Subjects,
+        students: &Students,
+        preferences: &[(StudentId, DivisionId)],
+    ) -> Self {
+        let count = students.values().count();
+        Metrics {
+            unassigned: students
+                .values()
+                .flat_map(|s| s.subjects.iter())
+                .filter(|(_, status)| matches!(status, Status::Undefined))
+                .count() as f64,
+            fill_variance: fill_variances(subjects).iter().map(|(_, v)| v).sum(),
+            demographics: demographics(subjects, students),
+            preferences: preferences
+                .iter()
+                .filter(|&&(student, division)| {
+                    students
+                        .get(student)
+                        .is_some_and(|s| !s.is_taking(division))
+                })
+                .count() as f64,
+            gaps: if count > 0 {
+                students.values().map(|s| gaps(s, subjects)).sum::<i32>() as f64
+                    / 60.0
+                    / count as f64
+            } else {
+                0.0
+            },
+        }
+    }
+
+    fn values(&self) -> [f64; 5] {
+        [
+            self.unassigned,
+            self.fill_variance,
+            self.demographics,
+            self.preferences,
+            self.gaps,
+        ]
+    }
+
+    fn values_mut(&mut self) -> [&mut f64; 5] {
+        [
+            &mut self.unassigned,
+            &mut self.fill_variance,
+            &mut self.demographics,
+            &mut self.preferences,
+            &mut self.gaps,
+        ]
+    }
+
+    /// The weighted sum of the metrics.
+    pub fn score(&self, weights: &Weights) -> f64 {
+        self.values()
+            .iter()
+            .zip(weights.0.values().iter())
+            .map(|(value, weight)| value * weight)
+            .sum()
+    }
+
+    /// One row per metric with its value, weight and weighted value, and a row for the score.
+    pub fn table(&self, weights: &Weights) -> Table {
+        let mut rows: Vec<Vec<String>> = NAMES
+            .iter()
+            .zip(self.values().iter().zip(weights.0.values().iter()))
+            .map(|(name, (value, weight))| {
+                vec![
+                    name.to_string(),
+                    format!("{:.4}", value),
+                    weight.to_string(),
+                    format!("{:.2}", value * weight),
+                ]
+            })
+            .collect();
+        rows.push(vec![
+            "합계".to_owned(),
+            String::new(),
+            String::new(),
+            format!("{:.2}", self.score(weights)),
+        ]);
+
+        Table {
+            header: ["항목", "값", "가중치", "점수"]
+                .iter()
+                .map(|&s| s.to_owned())
+                .collect(),
+            rows,
+        }
+    }
+}
+
+impl Weights {
+    /// Reads rows of (`항목`, `가중치`), `항목` being a name in `NAMES`. Returns the default
+    /// weights if there is no weight file.
+    pub fn read() -> Self {
+        let mut weights = Weights::default();
+        if !Path::new(PATH_WEIGHTS).exists() {
+            return weights;
+        }
+
+        let (header, records) = read_records(PATH_WEIGHTS, SHEET_WEIGHTS, None);
+        for record in records {
+            let row: Row = record
+                .deserialize(Some(&header))
+                .unwrap_or_else(|e| panic!("{}: {}", PATH_WEIGHTS, e));
+            let i = NAMES
+                .iter()
+                .position(|&name| name == row.metric.trim())
+                .unwrap_or_else(|| panic!("unknown metric {:?}", row.metric));
+            *weights.0.values_mut()[i] = row.weight;
+        }
+        weights
+    }
+}
+
+/// Reads `PATH_PREFERENCES`, or nothing if there is no such file.
+pub fn read_preferences(subjects: &Subjects, students: &Students) -> Vec<(StudentId, DivisionId)> {
+    if !Path::new(PATH_PREFERENCES).exists() {
+        return Vec::new();
+    }
+    waitlist::read_preferences(PATH_PREFERENCES, subjects, students)
+}
+
+/// Population variance of the fill rates of the divisions of each subject, leaving out
+/// divisions without a quota.
+pub fn fill_variances(subjects: &Subjects) -> Vec<(SubjectId, f64)> {
+    SubjectId::all()
+        .map(|subject| {
+            let rates: Vec<f64> = subjects
+                .of(subject)
+                .iter()
+                .map(|&d| &subjects[d])
+                .filter(|d| d.quota > 0)
+                .map(|d| d.assigned as f64 / d.quota as f64)
+                .collect();
+            if rates.is_empty() {
+                return (subject, 0.0);
+            }
+            let mean = rates.iter().sum::<f64>() / rates.len() as f64;
+            let variance =
+                rates.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / rates.len() as f64;
+            (subject, variance)
+        })
+        .collect()
+}
+
+/// One row per subject with the variance of the fill rates of its divisions.
+pub fn fill_variance_table(subjects: &Subjects) -> Table {
+    Table {
+        header: vec!["과목번호".to_owned(), "충원율 분산".to_owned()],
+        rows: fill_variances(subjects)
+            .iter()
+            .map(|(subject, variance)| vec![subject.to_string(), format!("{:.4}", variance)])
+            .collect(),
+    }
+}
+
+fn demographics(subjects: &Subjects, students: &Students) -> f64 {
+    let attributes: [fn(&Student) -> &String; 3] = [
+        |s| &s.gender,
+        |s| &s.nationality,
+        |s| &s.high_school_category,
+    ];
+    let (mut total, mut count) = (0.0, 0);
+    for subject in SubjectId::all() {
+        let takers: Vec<&Student> = students
+            .values()
+            .filter(|s| s.subjects[subject].division().is_some())
+            .collect();
+        for &division in subjects.of(subject) {
+            let members: Vec<&Student> = takers
+                .iter()
+                .copied()
+                .filter(|s| s.is_taking(division))
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            for &f in &attributes {
+                let (all, here) = (shares(&takers, f), shares(&members, f));
+                let distance: f64 = all
+                    .iter()
+                    .map(|(value, share)| (share - here.get(value).unwrap_or(&0.0)).abs())
+                    .sum();
+                total += distance / 2.0;
+                count += 1;
+            }
+        }
+    }
+
+    if count > 0 {
+        total / count as f64
+    } else {
+        0.0
+    }
+}
+
+/// Share of every value of `f` among `members`.
+fn shares<'a>(members: &[&'a Student], f: fn(&Student) -> &String) -> BTreeMap<&'a str, f64> {
+    let mut shares: BTreeMap<&str, f64> = BTreeMap::new();
+    for &s in members {
+        *shares.entry(f(s).as_str()).or_default() += 1.0 / members.len() as f64;
+    }
+    shares
+}
+
+/// Minutes a student waits between classes on the same day over a week.
+fn gaps(student: &Student, subjects: &Subjects) -> i32 {
+    let mut hours: Vec<[i32; 2]> = student
+        .subjects(subjects)
+        .iter()
+        .flat_map(|d| d.hour.iter().copied())
+        .collect();
+    hours.sort_unstable();
+
+    // intervals can overlap, for labs meeting in alternate weeks
+    let (mut gaps, mut end) = (0, None::<i32>);
+    for h in hours {
+        match end {
+            Some(e) if e / DAY == h[0] / DAY => {
+                gaps += (h[0] - e).max(0);
+                end = Some(e.max(h[1]));
+            }
+            _ => end = Some(h[1]),
+        }
+    }
+    gaps
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{student::Statuses, subject::Division};
+
+    fn student(id: &str, gender: &str, cs101: Status) -> Student {
+        let mut subjects = Statuses::new(Status::No);
+        subjects[SubjectId::of("CS101")] = cs101;
+        Student {
+            id: id.to_owned(),
+            gender: gender.to_owned(),
+            subjects,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn measure_simple() {
+        let division = |id: &str, division: &str, assigned, hour| Division {
+            id: id.to_owned(),
+            division: division.to_owned(),
+            assigned,
+            quota: 2,
+            hour: vec![hour],
+            ..Default::default()
+        };
+        let subjects = Subjects::new(vec![
+            division("CS101", "A", 2, [540, 600]),
+            division("CS101", "B", 0, [660, 720]),
+            division("MAS101", "A", 1, [720, 780]),
+        ]);
+        let (cs101, mas101) = (SubjectId::of("CS101"), SubjectId::of("MAS101"));
+        let (a, b) = (subjects.division(cs101, "A"), subjects.division(cs101, "B"));
+
+        let mut first = student("1", "남", Status::Enroll(a));
+        first.subjects[mas101] = Status::Enroll(subjects.division(mas101, "A"));
+        let students = Students::new(vec![
+            first,
+            student("2", "여", Status::Enroll(a)),
+            student("3", "여", Status::Undefined),
+        ]);
+        let id = |s| students.find(s).unwrap();
+
+        let metrics = Metrics::measure(&subjects, &students, &[(id("1"), b), (id("2"), a)]);
+        assert_eq!(metrics.unassigned, 1.0);
+        // CS101 divisions are full and empty, the single MAS101 one half full
+        assert_eq!(metrics.fill_variance, 0.25);
+        // every division has all of its subject's students
+        assert_eq!(metrics.demographics, 0.0);
+        assert_eq!(metrics.preferences, 1.0);
+        // two hours for the first student, between 10:00 and 12:00
+        assert_eq!(metrics.gaps, 2.0 / 3.0);
+
+        let weights = Weights::default();
+        assert_eq!(metrics.score(&weights), 1000.0 + 25.0 + 1.0 + 2.0 / 3.0);
+        assert_eq!(metrics.table(&weights).rows.len(), NAMES.len() + 1);
+    }
+}
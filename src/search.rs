@@ -0,0 +1,174 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{passes::Pass, report::Table, student::Students, subject::Subjects};
+
+/// One seeded run of a plan.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Run {
+    pub seed: u64,
+    /// Lower is better. `None` if a pass panicked, as passes do when a seed leads them into a
+    /// corner.
+    pub score: Option<f64>,
+}
+
+/// `count` seeds drawn from `seed`, so that a search can be repeated.
+pub fn seeds(seed: u64, count: usize) -> Vec<u64> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map(|_| rng.gen()).collect()
+}
+
+/// Runs `plan` once per seed on copies of `subjects` and `students`, on up to `threads`
+/// threads, scores each with `score`, and returns the runs best first and failed runs last.
+/// Nothing is saved; run the winning seed again to keep its assignment. Panic messages are not
+/// printed while it runs.
+pub fn search(
+    subjects: &Subjects,
+    students: &Students,
+    plan: &[(&str, Pass)],
+    seeds: &[u64],
+    threads: usize,
+    score: &(dyn Fn(&Subjects, &Students) -> f64 + Sync),
+) -> Vec<Run> {
+    let next = AtomicUsize::new(0);
+    // `Students` caches occupancy in cells, so every thread gets a copy of its own
+    let copies: Vec<Students> = (0..threads.clamp(1, seeds.len().max(1)))
+        .map(|_| students.clone())
+        .collect();
+
+    // a failed run is counted, not reported; the hook is global, so this covers every thread
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let mut runs: Vec<Run> = thread::scope(|scope| {
+        let workers: Vec<_> = copies
+            .into_iter()
+            .map(|students| {
+                let next = &next;
+                scope.spawn(move || {
+                    let mut runs = Vec::new();
+                    while let Some(&seed) = seeds.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let (mut subjects, mut students) = (subjects.clone(), students.clone());
+                        let completed = panic::catch_unwind(AssertUnwindSafe(|| {
+                            let mut rng = StdRng::seed_from_u64(seed);
+                            for (_, pass) in plan {
+                                pass(&mut subjects, &mut students, &mut rng);
+                            }
+                        }));
+                        runs.push(Run {
+                            seed,
+                            score: completed.ok().map(|_| score(&subjects, &students)),
+                        });
+                    }
+                    runs
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect()
+    });
+    panic::set_hook(hook);
+
+    runs.sort_by(|a, b| {
+        let score = |r: &Run| r.score.unwrap_or(f64::INFINITY);
+        (a.score.is_none().cmp(&b.score.is_none()))
+            .then(score(a).total_cmp(&score(b)))
+            .then(a.seed.cmp(&b.seed))
+    });
+    runs
+}
+
+/// How many runs got each score, to two decimal places, best first, and how many failed.
+pub fn distribution(runs: &[Run]) -> Table {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    // sorted, so runs with the same score are next to each other
+    for run in runs {
+        let score = run
+            .score
+            .map_or_else(|| "실패".to_owned(), |score| format!("{:.2}", score));
+        match rows.last_mut() {
+            Some(row) if row[0] == score => {
+                row[1] = (row[1].parse::<usize>().unwrap() + 1).to_string()
+            }
+            _ => rows.push(vec![score, "1".to_owned()]),
+        }
+    }
+
+    Table {
+        header: vec!["점수".to_owned(), "횟수".to_owned()],
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        ids::SubjectId,
+        score::{Metrics, Weights},
+        student::{Status, Statuses, Student},
+    };
+
+    fn student(id: &str) -> Student {
+        Student {
+            id: id.to_owned(),
+            subjects: Statuses::new(Status::Undefined),
+            ..Default::default()
+        }
+    }
+
+    /// Gives up on each subject of each student with even odds, or on the whole run one time in
+    /// four.
+    fn coin(_: &mut Subjects, students: &mut Students, rng: &mut StdRng) {
+        assert!(rng.gen_range(0..4) != 0, "bad seed");
+        for student in students.values_mut() {
+            for subject in SubjectId::all() {
+                if rng.gen() {
+                    student.subjects[subject] = Status::No;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn search_picks_best() {
+        let subjects = Subjects::new(Vec::new());
+        let students = Students::new((0..10).map(|i| student(&i.to_string())).collect());
+        let plan: &[(&str, Pass)] = &[("coin", coin)];
+        let seeds = seeds(1, 20);
+        let score = |subjects: &Subjects, students: &Students| {
+            Metrics::measure(subjects, students, &[]).score(&Weights::default())
+        };
+
+        let runs = search(&subjects, &students, plan, &seeds, 4, &score);
+        assert_eq!(runs.len(), 20);
+        let failed = runs.iter().filter(|r| r.score.is_none()).count();
+        assert!(failed > 0 && failed < 20);
+        // failed runs last
+        assert!(runs[20 - failed..].iter().all(|r| r.score.is_none()));
+        assert!(runs[..20 - failed]
+            .windows(2)
+            .all(|w| w[0].score <= w[1].score));
+        // the same on a single thread
+        assert_eq!(search(&subjects, &students, plan, &seeds, 1, &score), runs);
+
+        let table = distribution(&runs);
+        let total: usize = table
+            .rows
+            .iter()
+            .map(|r| r[1].parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(total, 20);
+        assert_eq!(table.rows[0][0], format!("{:.2}", runs[0].score.unwrap()));
+        assert_eq!(
+            table.rows.last().unwrap(),
+            &vec!["실패".to_owned(), failed.to_string()]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+};
+
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ids::SubjectId,
+    student::{Status, Statuses, Student, Students},
+    subject::{Division, Subjects, Weeks},
+};
+
+pub const PATH: &str = "./state.json";
+
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Metadata {
+    /// Version of saenaegi that wrote the state.
+    pub version: String,
+    /// RFC 3339 timestamp of when the state was written.
+    pub created: String,
+    /// Seed of the rng the assignment was made with.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// The last pass run before the state was written.
+    #[serde(default)]
+    pub pass: Option<String>,
+}
+
+impl Metadata {
+    pub fn now() -> Self {
+        Metadata {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            created: Local::now().to_rfc3339(),
+            seed: None,
+            pass: None,
+        }
+    }
+}
+
+/// `Division` without the quirks of the registrar's spreadsheet.
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct DivisionState {
+    id: String,
+    name: String,
+    division: String,
+    english: bool,
+    assigned: i32,
+    quota: i32,
+    hour: Vec<[i32; 2]>,
+    #[serde(default)]
+    weeks: Weeks,
+    #[serde(default)]
+    dates: Option<[NaiveDate; 2]>,
+    #[serde(default)]
+    building: String,
+    #[serde(default)]
+    room: String,
+    #[serde(default)]
+    travel: BTreeMap<String, i32>,
+    #[serde(default)]
+    instructors: Vec<String>,
+    /// Student ids (학번).
+    #[serde(default)]
+    waitlist: Vec<String>,
+}
+
+impl DivisionState {
+    fn new(d: &Division, students: &Students) -> Self {
+        DivisionState {
+            id: d.id.clone(),
+            name: d.name.clone(),
+            division: d.division.clone(),
+            english: d.english,
+            assigned: d.assigned,
+            quota: d.quota,
+            hour: d.hour.clone(),
+            weeks: d.weeks.clone(),
+            dates: d.dates,
+            building: d.building.clone(),
+            room: d.room.clone(),
+            travel: d.travel.clone(),
+            instructors: d.instructors.clone(),
+            waitlist: d
+                .waitlist
+                .iter()
+                .filter_map(|&id| students.get(id))
+                .map(|s| s.id.clone())
+                .collect(),
+        }
+    }
+
+    /// The division, and its waitlist to be looked up once the students are loaded.
+    fn into_division(self) -> (Division, Vec<String>) {
+        let mut division = Division {
+            id: self.id,
+            subject: Default::default(),
+            name: self.name,
+            division: self.division,
+            english: self.english,
+            assigned: self.assigned,
+            quota: self.quota,
+            hour: self.hour,
+            weeks: self.weeks,
+            dates: self.dates,
+            building: self.building,
+            room: self.room,
+            travel: self.travel,
+            instructors: self.instructors,
+            masks: None,
+            waitlist: Vec::new(),
+        };
+        division.update_masks();
+        (division, self.waitlist)
+    }
+}
+
+/// `Student` with subjects and divisions by name.
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct StudentState {
+    id: String,
+    name: String,
+    gender: String,
+    nationality: String,
+    high_school: String,
+    high_school_category: String,
+    subjects: BTreeMap<String, StatusState>,
+    pinned: BTreeSet<String>,
+}
+
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+enum StatusState {
+    Enroll(String),
+    No,
+    Undefined,
+}
+
+impl StudentState {
+    fn new(s: &Student, subjects: &Subjects) -> Self {
+        StudentState {
+            id: s.id.clone(),
+            name: s.name.clone(),
+            gender: s.gender.clone(),
+            nationality: s.nationality.clone(),
+            high_school: s.high_school.clone(),
+            high_school_category: s.high_school_category.clone(),
+            subjects: s
+                .subjects
+                .iter()
+                .map(|(subject, status)| {
+                    let status = match status {
+                        Status::Enroll(d) => StatusState::Enroll(subjects[*d].division.clone()),
+                        Status::No => StatusState::No,
+                        Status::Undefined => StatusState::Undefined,
+                    };
+                    (subject.name().to_owned(), status)
+                })
+                .collect(),
+            pinned: s.pinned.iter().map(|s| s.name().to_owned()).collect(),
+        }
+    }
+
+    fn into_student(self, subjects: &Subjects) -> Student {
+        let subject = |name: &str| {
+            SubjectId::parse(name).unwrap_or_else(|| panic!("unknown subject {:?}", name))
+        };
+        let mut statuses = Statuses::new(Status::Undefined);
+        for (name, status) in &self.subjects {
+            let subject = subject(name);
+            statuses[subject] = match status {
+                StatusState::Enroll(d) => Status::Enroll(subjects.division(subject, d)),
+                StatusState::No => Status::No,
+                StatusState::Undefined => Status::Undefined,
+            };
+        }
+
+        Student {
+            id: self.id,
+            name: self.name,
+            gender: self.gender,
+            nationality: self.nationality,
+            high_school: self.high_school,
+            high_school_category: self.high_school_category,
+            subjects: statuses,
+            pinned: self.pinned.iter().map(|name| subject(name)).collect(),
+            occupancy: Default::default(),
+        }
+    }
+}
+
+/// Everything needed to pick up an assignment where it was left off.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct State {
+    pub metadata: Metadata,
+    subjects: BTreeMap<String, BTreeMap<String, DivisionState>>,
+    students: BTreeMap<String, StudentState>,
+}
+
+impl State {
+    pub fn new(subjects: &Subjects, students: &Students) -> Self {
+        let mut divisions: BTreeMap<String, BTreeMap<String, DivisionState>> = BTreeMap::new();
+        for (_, d) in subjects.iter() {
+            divisions
+                .entry(d.subject.name().to_owned())
+                .or_default()
+                .insert(d.division.clone(), DivisionState::new(d, students));
+        }
+
+        State {
+            metadata: Metadata::now(),
+            subjects: divisions,
+            students: students
+                .values()
+                .map(|s| (s.id.clone(), StudentState::new(s, subjects)))
+                .collect(),
+        }
+    }
+
+    pub fn into_parts(self) -> (Subjects, Students) {
+        let mut divisions = Vec::new();
+        let mut waitlists = Vec::new();
+        for (subject_id, states) in self.subjects {
+            for (division_id, state) in states {
+                let (division, waitlist) = state.into_division();
+                divisions.push(division);
+                waitlists.push((subject_id.clone(), division_id, waitlist));
+            }
+        }
+
+        let mut subjects = Subjects::new(divisions);
+        let students = Students::new(
+            self.students
+                .into_values()
+                .map(|s| s.into_student(&subjects))
+                .collect(),
+        );
+        // students who withdrew are dropped from waitlists
+        for (subject_id, division_id, waitlist) in waitlists {
+            if let Some(division) = subjects.lookup(&subject_id, &division_id) {
+                subjects[division].waitlist =
+                    waitlist.iter().filter_map(|id| students.find(id)).collect();
+            }
+        }
+        (subjects, students)
+    }
+
+    pub fn save(&self, path: &str) {
+        fs::write(path, serde_json::to_string_pretty(self).unwrap()).unwrap();
+    }
+
+    pub fn load(path: &str) -> State {
+        serde_json::from_str(&fs::read_to_string(path).unwrap())
+            .unwrap_or_else(|e| panic!("{} is not a saved state: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut subjects = Subjects::new(vec![Division {
+            id: "CS101".to_owned(),
+            name: "프로그래밍기초".to_owned(),
+            division: "A ".to_owned(),
+            english: true,
+            assigned: 1,
+            quota: 40,
+            hour: vec![[9 * 60, 10 * 60 + 30]],
+            weeks: Weeks::Odd,
+            dates: Some([
+                NaiveDate::from_ymd_opt(2021, 3, 2).unwrap(),
+                NaiveDate::from_ymd_opt(2021, 4, 23).unwrap(),
+            ]),
+            building: "E11".to_owned(),
+            room: "101".to_owned(),
+            travel: vec![("N1".to_owned(), 15)].into_iter().collect(),
+            instructors: vec!["김교수".to_owned(), "이교수".to_owned()],
+            ..Default::default()
+        }]);
+        let cs101 = SubjectId::of("CS101");
+
+        let student = |id: &str| Student {
+            id: id.to_owned(),
+            name: "김가야".to_owned(),
+            gender: "여".to_owned(),
+            nationality: "대한민국".to_owned(),
+            high_school: "새내기고".to_owned(),
+            high_school_category: "일반고".to_owned(),
+            subjects: Statuses::new(Status::Undefined),
+            ..Default::default()
+        };
+        let mut enrolled = student("20210001");
+        enrolled.subjects[cs101] = Status::Enroll(subjects.division(cs101, "A"));
+        enrolled.subjects[SubjectId::of("BS120")] = Status::No;
+        enrolled.pinned.insert(cs101);
+        let students = Students::new(vec![enrolled, student("20210002")]);
+
+        let division = subjects.division(cs101, "A");
+        // as `Subjects::read` and `State::load` leave it
+        subjects[division].update_masks();
+        subjects[division].waitlist = vec![students.find("20210002").unwrap()];
+
+        let path = std::env::temp_dir().join("saenaegi_round_trip.json");
+        let path = path.to_str().unwrap();
+        State::new(&subjects, &students).save(path);
+        let (loaded_subjects, loaded_students) = State::load(path).into_parts();
+
+        assert_eq!(loaded_subjects, subjects);
+        assert_eq!(loaded_students, students);
+    }
+}
@@ -1,10 +1,24 @@
-use std::{collections::HashMap, fmt, fs};
+use std::{
+    cell::Cell,
+    collections::{BTreeSet, HashMap},
+    fmt,
+    ops::{Index, IndexMut},
+};
 
-use crate::subject::Division;
+use crate::{
+    ids::{DivisionId, StudentId, SubjectId},
+    input::read_records,
+    subject::{Division, Masks, Subjects},
+};
 
 const PATH: &str = "./data/csv/fin02.csv";
+/// Sheet to read when `PATH` is an `.xlsx` workbook.
+const SHEET: &str = "fin02";
+/// Encoding of `PATH` if it is a CSV file, or `None` to detect it.
+const ENCODING: Option<&str> = None;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(test, derive(Default))]
 pub struct Student {
     pub id: String,
     pub name: String,
@@ -12,10 +26,40 @@ pub struct Student {
     pub nationality: String,
     pub high_school: String,
     pub high_school_category: String,
-    pub subjects: HashMap<String, Status>,
+    pub subjects: Statuses,
+    /// Subjects whose division was given in the input rather than assigned by us.
+    pub pinned: BTreeSet<SubjectId>,
+    pub occupancy: Occupancy,
 }
 
-const SUBJECTS: &[&str] = &[
+/// The union of the masks of a student's divisions, or `None` inside if one of them has no
+/// mask. Computed on first use and cleared by `passes::assign` and `passes::unassign`, so any
+/// other change to an enrolled division must `clear` it too.
+#[derive(Clone, Default)]
+pub struct Occupancy(Cell<Option<Option<Masks>>>);
+
+impl Occupancy {
+    pub fn clear(&self) {
+        self.0.set(None);
+    }
+}
+
+// a cache rather than part of the student
+impl PartialEq for Occupancy {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Occupancy {}
+
+impl fmt::Debug for Occupancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Occupancy")
+    }
+}
+
+pub const SUBJECTS: &[&str] = &[
     "CS101",  // 프밍기
     "BS120",  // 일생
     "CH101",  // 일화
@@ -33,36 +77,190 @@ const SUBJECTS: &[&str] = &[
     "HSS024", // Adv.W
 ];
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Status {
-    Enroll(String),
+    Enroll(DivisionId),
     No,
     Undefined,
 }
 
-impl fmt::Display for Status {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Self::Enroll(s) => if s == "" { " " } else { s },
-                Self::No => "-",
-                Self::Undefined => "?",
-            }
-        )
+impl Status {
+    /// Reads a status as the student list gives it: `Undefined`, `-` or a division of `subject`.
+    pub fn parse(s: &str, subject: SubjectId, subjects: &Subjects) -> Result<Status, String> {
+        match s.trim() {
+            "Undefined" => Ok(Status::Undefined),
+            "-" => Ok(Status::No),
+            division => subjects
+                .find(subject, division)
+                .map(Status::Enroll)
+                .ok_or_else(|| format!("no division {} {}", subject, division)),
+        }
+    }
+
+    /// The status as the output shows it.
+    pub fn name(&self, subjects: &Subjects) -> String {
+        match self {
+            Self::Enroll(d) => subjects[*d].division.clone(),
+            Self::No => "-".to_owned(),
+            Self::Undefined => "?".to_owned(),
+        }
+    }
+
+    pub fn division(&self) -> Option<DivisionId> {
+        match self {
+            Self::Enroll(d) => Some(*d),
+            _ => None,
+        }
+    }
+}
+
+/// A student's status in every subject, by `SubjectId`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Statuses(Vec<Status>);
+
+/// Not taking any subject, for building students in tests.
+#[cfg(test)]
+impl Default for Statuses {
+    fn default() -> Self {
+        Statuses::new(Status::No)
+    }
+}
+
+impl Statuses {
+    /// `status` in every subject.
+    pub fn new(status: Status) -> Self {
+        Statuses(vec![status; SUBJECTS.len()])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SubjectId, &Status)> {
+        SubjectId::all().zip(&self.0)
+    }
+
+    /// The subjects the student is enrolled in, with their division.
+    pub fn enrolled(&self) -> impl Iterator<Item = (SubjectId, DivisionId)> + '_ {
+        self.iter()
+            .filter_map(|(subject, status)| Some((subject, status.division()?)))
+    }
+}
+
+impl Index<SubjectId> for Statuses {
+    type Output = Status;
+
+    fn index(&self, subject: SubjectId) -> &Status {
+        &self.0[subject.index()]
+    }
+}
+
+impl IndexMut<SubjectId> for Statuses {
+    fn index_mut(&mut self, subject: SubjectId) -> &mut Status {
+        &mut self.0[subject.index()]
+    }
+}
+
+/// Every student by `StudentId`, with a table from their student ids (학번). A student who
+/// withdraws leaves a hole, so that the ids of the others stay put.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Students {
+    students: Vec<Option<Student>>,
+    ids: HashMap<String, StudentId>,
+}
+
+impl Students {
+    /// Ids are given in the order of student ids.
+    pub fn new(mut students: Vec<Student>) -> Self {
+        students.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut arena = Students::default();
+        for student in students {
+            arena.insert(student);
+        }
+        arena
+    }
+
+    pub fn insert(&mut self, student: Student) -> StudentId {
+        let id = StudentId::new(self.students.len());
+        assert!(
+            self.ids.insert(student.id.clone(), id).is_none(),
+            "{} is already enrolled",
+            student.id
+        );
+        self.students.push(Some(student));
+        id
+    }
+
+    pub fn remove(&mut self, id: StudentId) -> Student {
+        let student = self.students[id.index()]
+            .take()
+            .expect("student already withdrew");
+        self.ids.remove(&student.id);
+        student
+    }
+
+    /// The student with the student id `id`, unless they withdrew.
+    pub fn find(&self, id: &str) -> Option<StudentId> {
+        self.ids.get(id.trim()).copied()
+    }
+
+    /// `None` if the student withdrew.
+    pub fn get(&self, id: StudentId) -> Option<&Student> {
+        self.students[id.index()].as_ref()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (StudentId, &Student)> {
+        self.students
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| Some((StudentId::new(i), s.as_ref()?)))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Student> {
+        self.students.iter().flatten()
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Student> {
+        self.students.iter_mut().flatten()
+    }
+}
+
+impl Index<StudentId> for Students {
+    type Output = Student;
+
+    fn index(&self, id: StudentId) -> &Student {
+        self.get(id).expect("student withdrew")
+    }
+}
+
+impl IndexMut<StudentId> for Students {
+    fn index_mut(&mut self, id: StudentId) -> &mut Student {
+        self.students[id.index()]
+            .as_mut()
+            .expect("student withdrew")
     }
 }
 
 impl Student {
-    pub fn read_csv() -> HashMap<String, Student> {
-        let mut reader = csv::Reader::from_path(PATH).unwrap();
-        reader
-            .records()
-            .map(|r| {
-                let r = r.unwrap();
-                (
-                    r[1].to_owned(),
+    pub fn read(subjects: &Subjects) -> Students {
+        Self::read_from(PATH, SHEET, ENCODING, subjects)
+    }
+
+    /// Reads students laid out like `PATH` from another file.
+    pub fn read_from(
+        path: &str,
+        sheet: &str,
+        encoding: Option<&str>,
+        subjects: &Subjects,
+    ) -> Students {
+        Students::new(
+            read_records(path, sheet, encoding)
+                .1
+                .into_iter()
+                .map(|r| {
+                    let mut statuses = Statuses::new(Status::Undefined);
+                    for (i, subject) in SubjectId::all().enumerate() {
+                        statuses[subject] = Status::parse(&r[i + 9], subject, subjects)
+                            .unwrap_or_else(|e| panic!("{}: {}: {}", path, &r[1], e));
+                    }
+                    let pinned = statuses.enrolled().map(|(subject, _)| subject).collect();
+
                     Student {
                         id: r[1].to_owned(),
                         name: r[2].to_owned(),
@@ -70,74 +268,55 @@ impl Student {
                         nationality: r[6].to_owned(),
                         high_school_category: r[7].to_owned(),
                         high_school: r[8].to_owned(),
-                        subjects: {
-                            let mut map = HashMap::new();
-                            for i in 9..24 {
-                                map.insert(
-                                    SUBJECTS[i - 9].to_owned(),
-                                    match &r[i] {
-                                        "Undefined" => Status::Undefined,
-                                        "-" => Status::No,
-                                        s => Status::Enroll(s.trim().to_owned()),
-                                    },
-                                );
-                            }
-                            map
-                        },
-                    },
-                )
-            })
-            .collect()
+                        subjects: statuses,
+                        pinned,
+                        occupancy: Occupancy::default(),
+                    }
+                })
+                .collect(),
+        )
     }
 
-    pub fn subjects<'a>(
-        &'a self,
-        subjects: &'a HashMap<String, HashMap<String, Division>>,
-    ) -> Vec<&'a Division> {
+    pub fn subjects<'a>(&self, subjects: &'a Subjects) -> Vec<&'a Division> {
         self.subjects
-            .iter()
-            .filter_map(|(subject_id, status)| match status {
-                Status::Enroll(division) => Some(&subjects[subject_id][division]),
-                _ => None,
-            })
+            .enrolled()
+            .map(|(_, division)| &subjects[division])
             .collect()
     }
 
-    pub fn is_taking(&self, id: &str, division: &str) -> bool {
-        match &self.subjects[id] {
-            Status::Enroll(s) => s.trim() == division,
-            _ => false,
+    /// Whether `division` fits the student's timetable, leaving out the division of its subject
+    /// the student is currently in.
+    pub fn fits(&self, division: DivisionId, subjects: &Subjects) -> bool {
+        let division = &subjects[division];
+        if let (Some(masks), Some(occupied)) = (&division.masks, self.occupancy(subjects)) {
+            // the student's own division of the subject would have to be left out
+            if self.subjects[division.subject].division().is_none() {
+                if let Some(clash) = masks.clash(&occupied) {
+                    return !clash;
+                }
+            }
         }
+
+        self.subjects
+            .enrolled()
+            .all(|(other, d)| other == division.subject || subjects[d].is_disjoint(division))
     }
 
-    pub fn print_csv(students: &[Student]) {
-        let mut buf = "학번, 이름, 성별, 국적, 고교구분, 출신고교, 프밍기, 일생, 일화, 일물, 고급물리, 실험물리, 미적, 일화실, 일물실, 010, 011, 023, 022, 025, 024\n".to_string();
-        for student in students {
-            buf.push_str(&format!(
-                "{:?},{:?},{:?},{:?},{:?},{:?},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
-                student.id,
-                student.name,
-                student.gender,
-                student.nationality,
-                student.high_school_category,
-                student.high_school,
-                student.subjects["CS101"],
-                student.subjects["BS120"],
-                student.subjects["CH101"],
-                student.subjects["PH141"],
-                student.subjects["PH161"],
-                student.subjects["PH171"],
-                student.subjects["MAS101"],
-                student.subjects["CH102"],
-                student.subjects["PH151"],
-                student.subjects["HSS010"],
-                student.subjects["HSS011"],
-                student.subjects["HSS023"],
-                student.subjects["HSS022"],
-                student.subjects["HSS025"],
-                student.subjects["HSS024"],
-            ));
+    fn occupancy(&self, subjects: &Subjects) -> Option<Masks> {
+        if let Some(mask) = self.occupancy.0.get() {
+            return mask;
         }
-        fs::write("./output.csv", buf).unwrap();
+        let mask = self
+            .subjects(subjects)
+            .iter()
+            .try_fold(Masks::EMPTY, |acc, d| {
+                d.masks.as_ref().map(|m| acc.union(m))
+            });
+        self.occupancy.0.set(Some(mask));
+        mask
+    }
+
+    pub fn is_taking(&self, division: DivisionId) -> bool {
+        self.subjects.enrolled().any(|(_, d)| d == division)
     }
 }
@@ -1,15 +1,51 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    ops::{Index, IndexMut},
+    path::Path,
+};
 
+use chrono::NaiveDate;
 use de::Unexpected;
-use serde::{de, Deserialize, Deserializer};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::{
+    ids::{DivisionId, StudentId, SubjectId},
+    input::read_records,
+    report::division_order,
+};
 
 const PATH: &str = "./data/csv/1. 2021년 봄학기 기초필수교과목.csv";
 const PATH_ENGLISH: &str = "./data/csv/3. 영어과목 반배정 - 과목리스트.csv";
+/// Sheets to read when the sources above are `.xlsx` workbooks.
+const SHEET: &str = "기초필수교과목";
+const SHEET_ENGLISH: &str = "과목리스트";
+/// Minutes needed between classes in two buildings, as rows of `건물1`, `건물2` and `분`.
+/// Optional; without it classes only must not overlap.
+const PATH_TRAVEL: &str = "./data/csv/travel.csv";
+const SHEET_TRAVEL: &str = "이동시간";
+/// Encoding of the CSV sources, or `None` to detect it.
+const ENCODING: Option<&str> = None;
+
+pub const DAYS: &[&str] = &["월", "화", "수", "목", "금", "토", "일"];
+const DAYS_ENGLISH: &[&str] = &[
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
 
-#[derive(Debug, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[cfg_attr(test, derive(Default))]
 pub struct Division {
     #[serde(rename = "과목번호")]
     pub id: String,
+    /// `id` as a `SubjectId`, set by `Subjects::new`.
+    #[serde(skip)]
+    pub subject: SubjectId,
     #[serde(rename = "과목명")]
     pub name: String,
     #[serde(rename = "분반")]
@@ -22,28 +58,184 @@ pub struct Division {
     pub quota: i32,
     #[serde(rename = "강의시간", deserialize_with = "intervals_from_string")]
     pub hour: Vec<[i32; 2]>,
+    /// Weeks of the semester the division meets in, for labs meeting every other week.
+    #[serde(rename = "주차", default, deserialize_with = "weeks_from_string")]
+    pub weeks: Weeks,
+    /// First and last day the division meets, if it does not run all semester.
+    #[serde(rename = "기간", default, deserialize_with = "dates_from_string")]
+    pub dates: Option<[NaiveDate; 2]>,
+    #[serde(rename = "건물", default)]
+    pub building: String,
+    #[serde(rename = "강의실", default)]
+    pub room: String,
+    /// Minutes needed between this division and one in each other building, from `PATH_TRAVEL`.
+    #[serde(skip)]
+    pub travel: BTreeMap<String, i32>,
+    /// Everyone teaching the division, from a comma-separated `교수`.
+    #[serde(rename = "교수", default, deserialize_with = "names_from_string")]
+    pub instructors: Vec<String>,
+    /// `hour` as bitsets, for checking most conflicts without comparing intervals. Set by
+    /// `update_masks`, which must follow any change to `hour`, `weeks`, `dates` or `travel`.
+    #[serde(skip)]
+    pub masks: Option<Masks>,
+    /// Students waiting for a seat, in the order they will be offered one.
+    #[serde(skip)]
+    pub waitlist: Vec<StudentId>,
 }
 
-impl Division {
-    pub fn read_csv() -> HashMap<String, HashMap<String, Division>> {
-        let mut map: HashMap<String, HashMap<String, Division>> = HashMap::new();
+/// Every division of the catalog by `DivisionId`, in the order of `SUBJECTS` and then of
+/// `division_order`. Divisions of subjects not in `SUBJECTS` are left out, since no student
+/// can be placed in them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Subjects {
+    divisions: Vec<Division>,
+    /// The divisions of every subject, by `SubjectId`.
+    by_subject: Vec<Vec<DivisionId>>,
+}
 
-        for division in csv::Reader::from_path(PATH)
-            .unwrap()
-            .deserialize()
-            .chain(csv::Reader::from_path(PATH_ENGLISH).unwrap().deserialize())
-        {
-            let division: Division = division.unwrap();
-            map.entry(division.id.trim().to_owned())
-                .or_default()
-                .insert(division.division.trim().to_owned(), division);
+impl Subjects {
+    /// Trims `id`, `name`, `division`, `building` and `room` of every division, so that nothing
+    /// past here needs to.
+    pub fn new(divisions: Vec<Division>) -> Self {
+        let mut divisions: Vec<Division> = divisions
+            .into_iter()
+            .filter_map(|mut division| {
+                division.subject = SubjectId::parse(&division.id)?;
+                for field in [
+                    &mut division.id,
+                    &mut division.name,
+                    &mut division.division,
+                    &mut division.building,
+                    &mut division.room,
+                ] {
+                    *field = field.trim().to_owned();
+                }
+                Some(division)
+            })
+            .collect();
+        divisions.sort_by(|a, b| {
+            (a.subject, division_order(&a.division)).cmp(&(b.subject, division_order(&b.division)))
+        });
+
+        let mut by_subject = vec![Vec::new(); SubjectId::all().count()];
+        for (i, division) in divisions.iter().enumerate() {
+            let ids: &mut Vec<DivisionId> = &mut by_subject[division.subject.index()];
+            // sorted, so a division listed twice comes right after itself
+            if let Some(last) = ids.last().map(|id| &divisions[id.index()]) {
+                assert!(
+                    last.division != division.division,
+                    "{} {} is listed twice",
+                    division.subject,
+                    division.division
+                );
+            }
+            ids.push(DivisionId::new(i));
         }
 
-        map
+        Subjects {
+            divisions,
+            by_subject,
+        }
+    }
+
+    pub fn read() -> Self {
+        let mut divisions = Vec::new();
+        for (path, sheet) in &[(PATH, SHEET), (PATH_ENGLISH, SHEET_ENGLISH)] {
+            let (header, records) = read_records(path, sheet, ENCODING);
+            for record in records {
+                let division: Division = record
+                    .deserialize(Some(&header))
+                    .unwrap_or_else(|e| panic!("{}: {}", path, e));
+                divisions.push(division);
+            }
+        }
+
+        let mut subjects = Self::new(divisions);
+        if Path::new(PATH_TRAVEL).exists() {
+            for (lhs, rhs, minutes) in read_travel(PATH_TRAVEL) {
+                for division in &mut subjects.divisions {
+                    if division.building == lhs {
+                        division.travel.insert(rhs.clone(), minutes);
+                    }
+                    if division.building == rhs {
+                        division.travel.insert(lhs.clone(), minutes);
+                    }
+                }
+            }
+        }
+
+        for division in &mut subjects.divisions {
+            division.update_masks();
+        }
+        subjects
+    }
+
+    /// The divisions of `subject`, in `division_order`.
+    pub fn of(&self, subject: SubjectId) -> &[DivisionId] {
+        &self.by_subject[subject.index()]
+    }
+
+    pub fn find(&self, subject: SubjectId, division: &str) -> Option<DivisionId> {
+        self.of(subject)
+            .iter()
+            .copied()
+            .find(|&id| self[id].division == division.trim())
+    }
+
+    /// The division named `division` of `subject`, which must be in the catalog.
+    pub fn division(&self, subject: SubjectId, division: &str) -> DivisionId {
+        self.find(subject, division)
+            .unwrap_or_else(|| panic!("no division {} {}", subject, division))
+    }
+
+    /// Looks up a division by the names a file gives for it.
+    pub fn lookup(&self, subject: &str, division: &str) -> Option<DivisionId> {
+        self.find(SubjectId::parse(subject)?, division)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (DivisionId, &Division)> {
+        self.divisions
+            .iter()
+            .enumerate()
+            .map(|(i, division)| (DivisionId::new(i), division))
+    }
+}
+
+impl Index<DivisionId> for Subjects {
+    type Output = Division;
+
+    fn index(&self, id: DivisionId) -> &Division {
+        &self.divisions[id.index()]
+    }
+}
+
+impl IndexMut<DivisionId> for Subjects {
+    fn index_mut(&mut self, id: DivisionId) -> &mut Division {
+        &mut self.divisions[id.index()]
+    }
+}
+
+impl Division {
+    pub fn update_masks(&mut self) {
+        self.masks = Masks::of(self);
     }
 
     pub fn is_disjoint(&self, rhs: &Self) -> bool {
-        disjoint_intervals(&self.hour, &rhs.hour)
+        if let (Some(lhs), Some(rhs)) = (&self.masks, &rhs.masks) {
+            if let Some(clash) = lhs.clash(rhs) {
+                return !clash;
+            }
+        }
+        !self.weeks.overlaps(&rhs.weeks)
+            || !overlapping_dates(self.dates, rhs.dates)
+            || disjoint_intervals(&self.hour, &rhs.hour, self.gap(rhs))
+    }
+
+    /// Minutes needed between the end of one division and the start of the other.
+    fn gap(&self, rhs: &Self) -> i32 {
+        let lhs_to_rhs = self.travel.get(&rhs.building);
+        let rhs_to_lhs = rhs.travel.get(&self.building);
+        lhs_to_rhs.max(rhs_to_lhs).copied().unwrap_or(0)
     }
 
     pub fn read_hss022_students() -> &'static [&'static str] {
@@ -51,14 +243,231 @@ impl Division {
     }
 }
 
+/// Resolution of `Mask`, in minutes.
+const MASK_SLOT: i32 = 5;
+const MASK_SLOTS: usize = 7 * 24 * 60 / MASK_SLOT as usize;
+const MASK_WORDS: usize = MASK_SLOTS.div_ceil(64);
+
+/// A week as one bit per `MASK_SLOT` minutes from Monday 00:00, set while a division meets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Mask([u64; MASK_WORDS]);
+
+impl Mask {
+    pub const EMPTY: Mask = Mask([0; MASK_WORDS]);
+
+    /// `None` if an interval does not start and end on a slot within the week.
+    pub fn of(hour: &[[i32; 2]]) -> Option<Mask> {
+        let mut mask = Mask::EMPTY;
+        for h in hour {
+            if h[0] % MASK_SLOT != 0
+                || h[1] % MASK_SLOT != 0
+                || h[0] < 0
+                || h[1] > 7 * 24 * 60
+                || h[0] >= h[1]
+            {
+                return None;
+            }
+            for slot in (h[0] / MASK_SLOT) as usize..(h[1] / MASK_SLOT) as usize {
+                mask.0[slot / 64] |= 1 << (slot % 64);
+            }
+        }
+        Some(mask)
+    }
+
+    pub fn intersects(&self, rhs: &Mask) -> bool {
+        self.0.iter().zip(&rhs.0).any(|(lhs, rhs)| lhs & rhs != 0)
+    }
+
+    pub fn union(mut self, rhs: &Mask) -> Mask {
+        for (lhs, rhs) in self.0.iter_mut().zip(&rhs.0) {
+            *lhs |= rhs;
+        }
+        self
+    }
+
+    /// Also sets every slot within the week starting less than `minutes` after a set one ends or
+    /// ending less than `minutes` before one starts.
+    fn widen(&self, minutes: i32) -> Mask {
+        let by = ((minutes + MASK_SLOT - 1) / MASK_SLOT) as usize;
+        let mut mask = *self;
+        for slot in (0..MASK_SLOTS).filter(|slot| self.0[slot / 64] & 1 << (slot % 64) != 0) {
+            for slot in slot.saturating_sub(by)..(slot + by + 1).min(MASK_SLOTS) {
+                mask.0[slot / 64] |= 1 << (slot % 64);
+            }
+        }
+        mask
+    }
+}
+
+/// A division's `Mask` in odd and in even weeks, which together settle most conflicts: two
+/// divisions cannot clash if neither meets within the travel time of the other, and clash if
+/// both meet at once in weeks they surely share.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Masks {
+    /// When the division meets, in odd and in even weeks.
+    meets: [Mask; 2],
+    /// `meets` widened by the longest travel time from the division's building.
+    reach: [Mask; 2],
+    /// `meets`, if the division meets in every week of the semester of those parities; empty
+    /// for a list of weeks or a date range.
+    sure: [Mask; 2],
+}
+
+impl Masks {
+    pub const EMPTY: Masks = Masks {
+        meets: [Mask::EMPTY; 2],
+        reach: [Mask::EMPTY; 2],
+        sure: [Mask::EMPTY; 2],
+    };
+
+    /// `None` if `Mask::of` the division's `hour` is.
+    fn of(division: &Division) -> Option<Masks> {
+        let mask = Mask::of(&division.hour)?;
+        let reach = mask.widen(division.travel.values().copied().max().unwrap_or(0));
+        let (odd, even) = match &division.weeks {
+            Weeks::List(weeks) => (
+                weeks.iter().any(|w| !w.is_multiple_of(2)),
+                weeks.iter().any(|w| w.is_multiple_of(2)),
+            ),
+            weeks => (weeks.contains(1), weeks.contains(2)),
+        };
+        let pick = |mask: Mask| [odd, even].map(|meets| if meets { mask } else { Mask::EMPTY });
+        let exact = division.dates.is_none() && !matches!(division.weeks, Weeks::List(_));
+
+        Some(Masks {
+            meets: pick(mask),
+            reach: pick(reach),
+            sure: if exact { pick(mask) } else { [Mask::EMPTY; 2] },
+        })
+    }
+
+    /// Whether divisions with these masks clash, or `None` if it takes comparing their intervals
+    /// to tell. Either side may be the union of the masks of several divisions.
+    pub fn clash(&self, rhs: &Masks) -> Option<bool> {
+        let intersect = |lhs: &[Mask; 2], rhs: &[Mask; 2]| {
+            lhs.iter().zip(rhs).any(|(lhs, rhs)| lhs.intersects(rhs))
+        };
+        if intersect(&self.sure, &rhs.sure) {
+            Some(true)
+        } else if !intersect(&self.meets, &rhs.reach) && !intersect(&self.reach, &rhs.meets) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    pub fn union(mut self, rhs: &Masks) -> Masks {
+        for (lhs, rhs) in [
+            (&mut self.meets, &rhs.meets),
+            (&mut self.reach, &rhs.reach),
+            (&mut self.sure, &rhs.sure),
+        ] {
+            for (lhs, rhs) in lhs.iter_mut().zip(rhs) {
+                *lhs = lhs.union(rhs);
+            }
+        }
+        self
+    }
+}
+
+/// Weeks of the semester a division meets in, the first week being 1.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Weeks {
+    #[default]
+    Every,
+    Odd,
+    Even,
+    List(BTreeSet<u32>),
+}
+
+impl Weeks {
+    /// Parses `매주`, `홀수주`, `짝수주` (or `every`, `odd`, `even`), or a list of weeks and
+    /// ranges such as `1,3,9-12`. An empty string is every week.
+    pub fn parse(s: &str) -> Result<Weeks, String> {
+        let s = s.trim();
+        let weeks = match s.to_lowercase().as_str() {
+            "" | "매주" | "every" => Weeks::Every,
+            "홀수" | "홀수주" | "odd" => Weeks::Odd,
+            "짝수" | "짝수주" | "even" => Weeks::Even,
+            list => {
+                let invalid = || format!("invalid weeks {:?}", s);
+                let week = |w: &str| match w.trim().parse::<u32>() {
+                    Ok(w) if w > 0 => Ok(w),
+                    _ => Err(invalid()),
+                };
+                let mut weeks = BTreeSet::new();
+                for part in list.trim_end_matches('주').split(',') {
+                    match part.split_once('-') {
+                        Some((first, last)) => {
+                            let (first, last) = (week(first)?, week(last)?);
+                            if first > last {
+                                return Err(invalid());
+                            }
+                            weeks.extend(first..=last);
+                        }
+                        None => {
+                            weeks.insert(week(part)?);
+                        }
+                    }
+                }
+                if weeks.is_empty() {
+                    return Err(invalid());
+                }
+                Weeks::List(weeks)
+            }
+        };
+        Ok(weeks)
+    }
+
+    pub fn contains(&self, week: u32) -> bool {
+        match self {
+            Weeks::Every => true,
+            Weeks::Odd => !week.is_multiple_of(2),
+            Weeks::Even => week.is_multiple_of(2),
+            Weeks::List(weeks) => weeks.contains(&week),
+        }
+    }
+
+    /// Whether some week is in both.
+    pub fn overlaps(&self, rhs: &Self) -> bool {
+        match (self, rhs) {
+            (Weeks::List(weeks), other) | (other, Weeks::List(weeks)) => {
+                weeks.iter().any(|&w| other.contains(w))
+            }
+            (Weeks::Odd, Weeks::Even) | (Weeks::Even, Weeks::Odd) => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Weeks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Weeks::Every => write!(f, "매주"),
+            Weeks::Odd => write!(f, "홀수주"),
+            Weeks::Even => write!(f, "짝수주"),
+            Weeks::List(weeks) => write!(
+                f,
+                "{}주",
+                weeks
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        }
+    }
+}
+
 // ref: https://github.com/serde-rs/serde/issues/1344
 fn bool_from_string<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: Deserializer<'de>,
 {
-    match String::deserialize(deserializer)?.as_ref() {
+    // empty spreadsheet cells come without the single space of the CSV export
+    match String::deserialize(deserializer)?.trim() {
         "Y" => Ok(true),
-        " " => Ok(false),
+        "" => Ok(false),
         other => Err(de::Error::invalid_value(
             Unexpected::Str(other),
             &"OK or nOK",
@@ -70,44 +479,178 @@ fn intervals_from_string<'de, D>(deserializer: D) -> Result<Vec<[i32; 2]>, D::Er
 where
     D: Deserializer<'de>,
 {
-    Ok(parse_intervals(String::deserialize(deserializer)?.as_ref()))
+    parse_intervals(String::deserialize(deserializer)?.as_ref()).map_err(de::Error::custom)
 }
 
-fn parse_intervals(s: &str) -> Vec<[i32; 2]> {
-    s.split("\r\n").map(parse_interval).collect()
+fn weeks_from_string<'de, D>(deserializer: D) -> Result<Weeks, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Weeks::parse(String::deserialize(deserializer)?.as_ref()).map_err(de::Error::custom)
+}
+
+fn names_from_string<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(String::deserialize(deserializer)?
+        .split([',', '/', ';'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+        .collect())
+}
+
+fn dates_from_string<'de, D>(deserializer: D) -> Result<Option<[NaiveDate; 2]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    parse_dates(String::deserialize(deserializer)?.as_ref()).map_err(de::Error::custom)
+}
+
+/// Parses `YYYY-MM-DD~YYYY-MM-DD`, or an empty string for the whole semester.
+pub fn parse_dates(s: &str) -> Result<Option<[NaiveDate; 2]>, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(None);
+    }
+
+    let invalid = || format!("invalid dates {:?}", s);
+    let (first, last) = s.split_once(['~', '～']).ok_or_else(invalid)?;
+    let date = |d: &str| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d").map_err(|_| invalid());
+    let (first, last) = (date(first)?, date(last)?);
+    if first > last {
+        return Err(invalid());
+    }
+    Ok(Some([first, last]))
+}
+
+/// Whether two date ranges share a day, `None` being the whole semester.
+fn overlapping_dates(lhs: Option<[NaiveDate; 2]>, rhs: Option<[NaiveDate; 2]>) -> bool {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => lhs[0] <= rhs[1] && rhs[0] <= lhs[1],
+        _ => true,
+    }
+}
+
+/// Parses lecture times as minutes from Monday 00:00, one `<days> HH:MM~HH:MM` per line, e.g.
+/// `월,수 09:00~10:30` or `Tue 13:00~14:30`.
+pub fn parse_intervals(s: &str) -> Result<Vec<[i32; 2]>, String> {
+    // `\r\n` in the CSV export, `\n` inside spreadsheet cells, `;` when typed on one line
+    let mut intervals = Vec::new();
+    for line in s
+        .split(['\n', ';'])
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+    {
+        intervals.extend(
+            parse_interval(line).map_err(|e| format!("invalid lecture time {:?}: {}", line, e))?,
+        );
+    }
+    Ok(intervals)
+}
+
+fn parse_interval(s: &str) -> Result<Vec<[i32; 2]>, String> {
+    let at = s.find(|c: char| c.is_ascii_digit()).ok_or("no time")?;
+    let (days, time) = s.split_at(at);
+
+    let time: String = time
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            // full-width tilde and wave dash
+            '～' | '〜' => '~',
+            c => c,
+        })
+        .collect();
+    let (start, end) = time.split_once('~').ok_or("no `~` between times")?;
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    if start >= end {
+        return Err(format!("{} does not end after it starts", time));
+    }
+
+    let days: Vec<i32> = days
+        .split([',', '/', '·'])
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| parse_day(d).ok_or_else(|| format!("unknown day {:?}", d)))
+        .collect::<Result<_, _>>()?;
+    if days.is_empty() {
+        return Err("no day".to_owned());
+    }
+
+    Ok(days
+        .into_iter()
+        .map(|day| [day * 24 * 60 + start, day * 24 * 60 + end])
+        .collect())
 }
 
-fn parse_interval(s: &str) -> [i32; 2] {
-    let mut s = s.split_whitespace();
+/// Index of a day in `DAYS`, also accepting English names and their three-letter abbreviations.
+fn parse_day(s: &str) -> Option<i32> {
+    let english = s.trim_end_matches('.').to_lowercase();
+    DAYS.iter()
+        .zip(DAYS_ENGLISH)
+        .position(|(&korean, &english_name)| {
+            s == korean || english == english_name || english == english_name[..3]
+        })
+        .map(|day| day as i32)
+}
+
+fn parse_time(s: &str) -> Result<i32, String> {
+    let (hour, minute) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid time {:?}", s))?;
+    match (hour.parse::<i32>(), minute.parse::<i32>()) {
+        // 24:00 ends a day, but 24:01 would be the next one
+        (Ok(hour), Ok(minute))
+            if (0..24).contains(&hour) && (0..60).contains(&minute)
+                || (hour, minute) == (24, 0) =>
+        {
+            Ok(hour * 60 + minute)
+        }
+        _ => Err(format!("invalid time {:?}", s)),
+    }
+}
 
-    let day = match s.next().unwrap() {
-        "월" => 0,
-        "화" => 1,
-        "수" => 2,
-        "목" => 3,
-        "금" => 4,
-        _ => unreachable!(),
-    } * 24
-        * 60;
+#[derive(Debug, Deserialize)]
+struct TravelRow {
+    #[serde(rename = "건물1")]
+    lhs: String,
+    #[serde(rename = "건물2")]
+    rhs: String,
+    #[serde(rename = "분")]
+    minutes: i32,
+}
 
-    let mut moment = s.next().unwrap().split('~').map(|t| {
-        t.split(':')
-            .map(|n| n.parse::<i32>().unwrap())
-            .fold(0, |acc, t| acc * 60 + t)
-    });
-    [day + moment.next().unwrap(), day + moment.next().unwrap()]
+/// Reads the minutes needed between two buildings, either way.
+fn read_travel(path: &str) -> Vec<(String, String, i32)> {
+    let (header, records) = read_records(path, SHEET_TRAVEL, ENCODING);
+    records
+        .iter()
+        .map(|record| {
+            let row: TravelRow = record
+                .deserialize(Some(&header))
+                .unwrap_or_else(|e| panic!("{}: {}", path, e));
+            (
+                row.lhs.trim().to_owned(),
+                row.rhs.trim().to_owned(),
+                row.minutes,
+            )
+        })
+        .collect()
 }
 
-fn disjoint_intervals(lhs: &[[i32; 2]], rhs: &[[i32; 2]]) -> bool {
+/// Whether no two intervals overlap or come closer than `gap` minutes.
+fn disjoint_intervals(lhs: &[[i32; 2]], rhs: &[[i32; 2]], gap: i32) -> bool {
     lhs.iter()
-        .all(|lhs| rhs.iter().all(|rhs| disjoint_interval(lhs, rhs)))
+        .all(|lhs| rhs.iter().all(|rhs| disjoint_interval(lhs, rhs, gap)))
 }
 
-fn disjoint_interval(lhs: &[i32; 2], rhs: &[i32; 2]) -> bool {
-    lhs[1] <= rhs[0] || rhs[1] <= lhs[0]
+fn disjoint_interval(lhs: &[i32; 2], rhs: &[i32; 2], gap: i32) -> bool {
+    lhs[1] + gap <= rhs[0] || rhs[1] + gap <= lhs[0]
 }
 
-/// students must be less than or equal to the sum of the quota
+// students must be less than or equal to the sum of the quota
 // pub fn distribute_students(quotas: &[i32], students: [i32; 6]) -> Vec<[i32; 6]> {
 //     let quotas_sum: i32 = quotas.iter().sum();
 //     let students_sum: i32 = students.iter().sum();
@@ -130,34 +673,226 @@ mod test {
     use super::*;
     #[test]
     fn parse_interval_simple() {
-        assert_eq!(parse_intervals("월 09:00~12:00"), vec![[9 * 60, 12 * 60]]);
+        assert_eq!(
+            parse_intervals("월 09:00~12:00"),
+            Ok(vec![[9 * 60, 12 * 60]])
+        );
         assert_eq!(
             parse_intervals("화 09:00~12:00"),
-            vec![[(24 + 9) * 60, (24 + 12) * 60]]
+            Ok(vec![[(24 + 9) * 60, (24 + 12) * 60]])
         );
         assert_eq!(
             parse_intervals("월 14:30~16:00\r\n수 14:30~16:00\r\n금 13:00~14:00"),
-            vec![
+            Ok(vec![
                 [14 * 60 + 30, 16 * 60],
                 [(24 * 2 + 14) * 60 + 30, (24 * 2 + 16) * 60],
                 [(24 * 4 + 13) * 60, (24 * 4 + 14) * 60],
-            ]
+            ])
+        );
+        assert_eq!(
+            parse_intervals("월 14:30~16:00\n수 14:30~16:00"),
+            parse_intervals("월 14:30~16:00\r\n수 14:30~16:00")
+        );
+    }
+
+    #[test]
+    fn parse_interval_extended() {
+        let expected = parse_intervals("월 09:00~10:30\n수 09:00~10:30");
+        assert_eq!(parse_intervals("월,수 09:00~10:30"), expected);
+        assert_eq!(
+            parse_intervals("Mon 09:00 ~ 10:30; Wednesday 09:00～10:30"),
+            expected
+        );
+        assert_eq!(
+            parse_intervals("토 10:00~12:00\r\n일 9:00~10:00"),
+            Ok(vec![
+                [(24 * 5 + 10) * 60, (24 * 5 + 12) * 60],
+                [(24 * 6 + 9) * 60, (24 * 6 + 10) * 60],
+            ])
+        );
+
+        assert!(parse_intervals("월 09:00~10:30\n월요일 09:00~10:30")
+            .unwrap_err()
+            .contains("월요일 09:00~10:30"));
+        assert!(parse_intervals("화 10:30~09:00").is_err());
+        assert!(parse_intervals("화 09:00").is_err());
+        assert!(parse_intervals("화 25:00~26:00").is_err());
+        assert!(parse_intervals("금 10:00~24:59").is_err());
+        assert_eq!(
+            parse_intervals("금 22:00~24:00"),
+            Ok(vec![[4 * 24 * 60 + 22 * 60, 5 * 24 * 60]])
+        );
+    }
+
+    #[test]
+    fn weeks_simple() {
+        assert_eq!(Weeks::parse(""), Ok(Weeks::Every));
+        assert_eq!(Weeks::parse("홀수주"), Ok(Weeks::Odd));
+        assert_eq!(
+            Weeks::parse("1,3,9-11주"),
+            Ok(Weeks::List(BTreeSet::from([1, 3, 9, 10, 11])))
+        );
+        assert!(Weeks::parse("0,2").is_err());
+        assert!(Weeks::parse("12-9").is_err());
+        assert!(Weeks::parse("1,12-9").is_err());
+
+        assert!(!Weeks::Odd.overlaps(&Weeks::Even));
+        assert!(Weeks::Odd.overlaps(&Weeks::Every));
+        assert!(Weeks::Even.overlaps(&Weeks::parse("1-2").unwrap()));
+        assert!(!Weeks::Even.overlaps(&Weeks::parse("1,3").unwrap()));
+    }
+
+    #[test]
+    fn dates_simple() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2021, m, d).unwrap();
+        assert_eq!(parse_dates(" "), Ok(None));
+        assert_eq!(
+            parse_dates("2021-03-02~2021-04-23"),
+            Ok(Some([date(3, 2), date(4, 23)]))
         );
+        assert!(parse_dates("2021-04-23~2021-03-02").is_err());
+
+        let first = parse_dates("2021-03-02~2021-04-23").unwrap();
+        let second = parse_dates("2021-04-26 ～ 2021-06-18").unwrap();
+        assert!(!overlapping_dates(first, second));
+        assert!(overlapping_dates(first, None));
     }
 
     #[test]
     fn disjoint_intervals_simple() {
         assert!(disjoint_intervals(
-            &parse_intervals("월 09:00~12:00"),
-            &parse_intervals("수 09:00~12:00")
+            &parse_intervals("월 09:00~12:00").unwrap(),
+            &parse_intervals("수 09:00~12:00").unwrap(),
+            0
         ));
         assert!(disjoint_intervals(
-            &parse_intervals("월 14:30~16:00\r\n수 14:30~16:00\r\n금 13:00~14:00"),
-            &parse_intervals("수 09:00~12:00")
+            &parse_intervals("월 14:30~16:00\r\n수 14:30~16:00\r\n금 13:00~14:00").unwrap(),
+            &parse_intervals("수 09:00~12:00").unwrap(),
+            0
         ));
         assert!(!disjoint_intervals(
-            &parse_intervals("월 14:30~16:00\r\n수 14:30~16:00\r\n금 13:00~14:00"),
-            &parse_intervals("월 12:30~14:00\r\n수 12:30~14:00\r\n금 13:00~14:00")
+            &parse_intervals("월 14:30~16:00\r\n수 14:30~16:00\r\n금 13:00~14:00").unwrap(),
+            &parse_intervals("월 12:30~14:00\r\n수 12:30~14:00\r\n금 13:00~14:00").unwrap(),
+            0
         ));
     }
+
+    #[test]
+    fn mask_agrees_with_intervals() {
+        let hours: Vec<Vec<[i32; 2]>> = [
+            "월 09:00~10:30",
+            "월 10:30~12:00",
+            "월 10:00~11:00\n수 13:00~14:30",
+            "수 14:25~15:00",
+            "일 23:00~24:00",
+        ]
+        .iter()
+        .map(|h| parse_intervals(h).unwrap())
+        .collect();
+
+        for lhs in &hours {
+            for rhs in &hours {
+                let (lhs_mask, rhs_mask) = (Mask::of(lhs).unwrap(), Mask::of(rhs).unwrap());
+                assert_eq!(
+                    !lhs_mask.intersects(&rhs_mask),
+                    disjoint_intervals(lhs, rhs, 0),
+                    "{:?} {:?}",
+                    lhs,
+                    rhs
+                );
+            }
+        }
+        assert_eq!(Mask::of(&parse_intervals("월 09:03~10:00").unwrap()), None);
+    }
+
+    #[test]
+    fn disjoint_with_travel() {
+        let division = |building: &str, hour: &str| Division {
+            id: String::new(),
+            division: String::new(),
+            hour: parse_intervals(hour).unwrap(),
+            building: building.to_owned(),
+            travel: [("E11", 20), ("N1", 20)]
+                .iter()
+                .filter(|(b, _)| *b != building)
+                .map(|&(b, minutes)| (b.to_owned(), minutes))
+                .collect(),
+            ..Default::default()
+        };
+
+        let first = division("E11", "월 09:00~10:15");
+        assert!(first.is_disjoint(&division("E11", "월 10:15~11:30")));
+        assert!(!first.is_disjoint(&division("N1", "월 10:30~11:45")));
+        assert!(first.is_disjoint(&division("N1", "월 10:35~11:50")));
+        assert!(first.is_disjoint(&division("N1", "화 10:15~11:30")));
+    }
+
+    #[test]
+    fn masks_agree_with_intervals() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2021, m, d).unwrap();
+        let division = |building: &str, hour: &str, weeks: Weeks, dates| Division {
+            hour: parse_intervals(hour).unwrap(),
+            weeks,
+            dates,
+            building: building.to_owned(),
+            travel: [("E11", 20), ("N1", 20)]
+                .iter()
+                .filter(|(b, _)| *b != building)
+                .map(|&(b, minutes)| (b.to_owned(), minutes))
+                .collect(),
+            ..Default::default()
+        };
+        let odd = Weeks::parse("홀수주").unwrap();
+        let divisions = [
+            division("E11", "월 09:00~10:15", Weeks::Every, None),
+            division("N1", "월 10:30~11:45", Weeks::Every, None),
+            division("N1", "월 10:35~11:50", Weeks::Every, None),
+            division("E11", "월 09:00~12:00", odd.clone(), None),
+            division("E11", "월 09:00~12:00", Weeks::Even, None),
+            division("E11", "월 09:30~10:00", Weeks::parse("1,3").unwrap(), None),
+            division(
+                "E11",
+                "월 09:30~10:00",
+                odd,
+                Some([date(4, 26), date(6, 18)]),
+            ),
+            division("N1", "화 09:00~10:00", Weeks::Every, None),
+        ];
+        let with_masks: Vec<Division> = divisions
+            .iter()
+            .cloned()
+            .map(|mut d| {
+                d.update_masks();
+                d
+            })
+            .collect();
+
+        for (lhs, lhs_masked) in divisions.iter().zip(&with_masks) {
+            for (rhs, rhs_masked) in divisions.iter().zip(&with_masks) {
+                assert_eq!(
+                    lhs_masked.is_disjoint(rhs_masked),
+                    lhs.is_disjoint(rhs),
+                    "{:?} {:?}",
+                    lhs.hour,
+                    rhs.hour
+                );
+            }
+        }
+
+        let clash = |i: usize, j: usize| {
+            let masks = |k: usize| with_masks[k].masks.unwrap();
+            masks(i).clash(&masks(j))
+        };
+        // within travel time, which depends on the buildings
+        assert_eq!(clash(0, 1), None);
+        assert_eq!(clash(0, 2), Some(false));
+        assert_eq!(clash(0, 3), Some(true));
+        // alternating weeks
+        assert_eq!(clash(3, 4), Some(false));
+        // the list and the date range may or may not share a week with the other
+        assert_eq!(clash(3, 5), None);
+        assert_eq!(clash(3, 6), None);
+        assert_eq!(clash(4, 6), Some(false));
+        assert_eq!(clash(0, 7), Some(false));
+    }
 }
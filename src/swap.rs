@@ -0,0 +1,315 @@
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
+use serde::Deserialize;
+
+use crate::{
+    ids::{DivisionId, StudentId, SubjectId},
+    incremental::Move,
+    input::read_records,
+    passes::{assign, unassign},
+    student::Students,
+    subject::Subjects,
+};
+
+/// A student asking to move from one division of a subject to another.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Request {
+    pub student: StudentId,
+    pub subject: SubjectId,
+    pub from: DivisionId,
+    pub to: DivisionId,
+}
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    #[serde(rename = "학번")]
+    student: String,
+    #[serde(rename = "과목번호")]
+    subject: String,
+    #[serde(rename = "현재분반")]
+    from: String,
+    #[serde(rename = "희망분반")]
+    to: String,
+}
+
+/// Reads rows of (`학번`, `과목번호`, `현재분반`, `희망분반`). Rows naming an unknown student
+/// or division are left out.
+pub fn read_requests(path: &str, subjects: &Subjects, students: &Students) -> Vec<Request> {
+    let (header, records) = read_records(path, "swaps", None);
+    records
+        .iter()
+        .filter_map(|record| {
+            let row: Row = record.deserialize(Some(&header)).unwrap();
+            let request = Request::lookup(&row, subjects, students);
+            if request.is_none() {
+                println!(
+                    "{} {}: {} -> {} unknown",
+                    row.student.trim(),
+                    row.subject.trim(),
+                    row.from.trim(),
+                    row.to.trim()
+                );
+            }
+            request
+        })
+        .collect()
+}
+
+impl Request {
+    fn lookup(row: &Row, subjects: &Subjects, students: &Students) -> Option<Request> {
+        let from = subjects.lookup(&row.subject, &row.from)?;
+        Some(Request {
+            student: students.find(&row.student)?,
+            subject: subjects[from].subject,
+            from,
+            to: subjects.lookup(&row.subject, &row.to)?,
+        })
+    }
+
+    /// Whether the request can still be granted on its own: the student is in `from`, not pinned
+    /// there, and `to` fits the rest of their timetable.
+    fn is_valid(&self, subjects: &Subjects, students: &Students) -> bool {
+        let student = match students.get(self.student) {
+            Some(student) => student,
+            None => return false,
+        };
+        self.from != self.to
+            && student.is_taking(self.from)
+            && !student.pinned.contains(&self.subject)
+            && student.fits(self.to, subjects)
+    }
+}
+
+/// Grants as many requests as possible without overfilling any division: first by trading
+/// seats around the shortest cycles of requests (a pairwise swap being a cycle of two), then by
+/// moving students into free seats, which may in turn close new cycles. Returns the moves made
+/// and the requests left ungranted.
+pub fn trade(
+    subjects: &mut Subjects,
+    students: &mut Students,
+    mut requests: Vec<Request>,
+) -> (Vec<Move>, Vec<Request>) {
+    let mut moves = Vec::new();
+
+    loop {
+        requests.retain(|r| r.is_valid(subjects, students));
+
+        if let Some(cycle) = shortest_cycle(&requests) {
+            let granted: Vec<Request> = cycle.iter().map(|&i| requests[i].clone()).collect();
+            // everyone leaves before anyone enters, so no division is ever over its quota
+            for r in &granted {
+                unassign(&mut students[r.student], r.subject, subjects);
+            }
+            for r in granted {
+                grant(subjects, students, r, &mut moves);
+            }
+            continue;
+        }
+
+        let free = requests
+            .iter()
+            .position(|r| subjects[r.to].assigned < subjects[r.to].quota);
+        match free {
+            Some(i) => {
+                let r = requests.remove(i);
+                unassign(&mut students[r.student], r.subject, subjects);
+                grant(subjects, students, r, &mut moves);
+            }
+            None => break,
+        }
+    }
+
+    (moves, requests)
+}
+
+fn grant(subjects: &mut Subjects, students: &mut Students, r: Request, moves: &mut Vec<Move>) {
+    let student = &mut students[r.student];
+    assign(student, r.to, subjects);
+    moves.push(Move::new(
+        student,
+        r.subject,
+        Some(r.from),
+        Some(r.to),
+        subjects,
+    ));
+}
+
+/// The shortest cycle of requests, each asking for the seat the next one leaves. Requests are
+/// indices into `requests`.
+///
+/// Searches the graph with divisions as nodes and requests as edges, breadth first from every
+/// division with a request leaving it. A student is in only one division of a subject, so the
+/// requests of a cycle through distinct divisions are all from distinct students.
+fn shortest_cycle(requests: &[Request]) -> Option<Vec<usize>> {
+    let mut leaving: HashMap<DivisionId, Vec<usize>> = HashMap::new();
+    let mut starts = Vec::new();
+    for (i, r) in requests.iter().enumerate() {
+        let out = leaving.entry(r.from).or_default();
+        if out.is_empty() {
+            starts.push(r.from);
+        }
+        out.push(i);
+    }
+
+    let mut best: Option<Vec<usize>> = None;
+    for start in starts {
+        // the request taken into every division reached so far
+        let mut via: HashMap<DivisionId, usize> = HashMap::new();
+        let mut queue = VecDeque::from(vec![start]);
+        'search: while let Some(division) = queue.pop_front() {
+            for &i in leaving.get(&division).map_or(&[][..], Vec::as_slice) {
+                let to = requests[i].to;
+                if to == start {
+                    let mut cycle = vec![i];
+                    let mut at = division;
+                    while at != start {
+                        cycle.push(via[&at]);
+                        at = requests[via[&at]].from;
+                    }
+                    cycle.reverse();
+                    if best.as_ref().is_none_or(|b| cycle.len() < b.len()) {
+                        best = Some(cycle);
+                    }
+                    break 'search;
+                }
+                if let Entry::Vacant(entry) = via.entry(to) {
+                    entry.insert(i);
+                    queue.push_back(to);
+                }
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        passes::prepare,
+        student::{Status, Student},
+        subject::Division,
+    };
+
+    fn request(student: usize, from: usize, to: usize) -> Request {
+        Request {
+            student: StudentId::new(student),
+            subject: SubjectId::of("CS101"),
+            from: DivisionId::new(from),
+            to: DivisionId::new(to),
+        }
+    }
+
+    #[test]
+    fn shortest_cycle_simple() {
+        // divisions A to D are 0 to 3
+        let requests = vec![
+            request(1, 0, 1),
+            request(2, 1, 2),
+            request(3, 2, 0),
+            request(4, 1, 0),
+            request(5, 2, 3),
+        ];
+        assert_eq!(shortest_cycle(&requests), Some(vec![0, 3]));
+        assert_eq!(shortest_cycle(&requests[1..]), None);
+        assert_eq!(
+            shortest_cycle(&[
+                requests[0].clone(),
+                requests[1].clone(),
+                requests[2].clone()
+            ]),
+            Some(vec![0, 1, 2])
+        );
+    }
+
+    #[test]
+    fn shortest_cycle_long_chain() {
+        // 20 students asking each of A -> B, B -> C, ..., I -> J
+        let chain: Vec<Request> = (0..9)
+            .flat_map(|d| (0..20).map(move |s| request(d * 20 + s, d, d + 1)))
+            .collect();
+        assert_eq!(shortest_cycle(&chain), None);
+
+        let mut closed = chain;
+        closed.push(request(1000, 9, 0));
+        let cycle = shortest_cycle(&closed).unwrap();
+        assert_eq!(cycle.len(), 10);
+        assert_eq!(cycle.last(), Some(&180));
+    }
+
+    #[test]
+    fn trade_simple() {
+        let division = |id: &str, division: &str, quota, hour| Division {
+            id: id.to_owned(),
+            division: division.to_owned(),
+            quota,
+            hour: vec![hour],
+            ..Default::default()
+        };
+        // CS101 C clashes with MAS101 A
+        let mut subjects = Subjects::new(vec![
+            division("CS101", "A", 2, [540, 600]),
+            division("CS101", "B", 4, [600, 660]),
+            division("CS101", "C", 1, [660, 720]),
+            division("MAS101", "A", 6, [660, 720]),
+        ]);
+        let (cs101, mas101) = (SubjectId::of("CS101"), SubjectId::of("MAS101"));
+        let [a, b, c] = [
+            subjects.division(cs101, "A"),
+            subjects.division(cs101, "B"),
+            subjects.division(cs101, "C"),
+        ];
+
+        let student = |id: &str, division| {
+            let mut student = Student {
+                id: id.to_owned(),
+                ..Default::default()
+            };
+            student.subjects[cs101] = Status::Enroll(division);
+            student
+        };
+        let mut third = student("3", a);
+        third.subjects[mas101] = Status::Enroll(subjects.division(mas101, "A"));
+        let mut fourth = student("4", b);
+        fourth.pinned.insert(cs101);
+        let mut students = Students::new(vec![
+            student("1", a),
+            student("2", b),
+            third,
+            fourth,
+            student("5", b),
+            student("6", b),
+        ]);
+        prepare(&mut subjects, &students);
+        let ask = |name, from, to| Request {
+            student: students.find(name).unwrap(),
+            subject: cs101,
+            from,
+            to,
+        };
+
+        let requests = vec![
+            // 1 and 2 swap; A is then full, so 6 is left waiting
+            ask("1", a, b),
+            ask("2", b, a),
+            ask("6", b, a),
+            // C clashes with MAS101 A
+            ask("3", a, c),
+            // pinned
+            ask("4", b, a),
+            // into the free seat
+            ask("5", b, c),
+        ];
+        let (moves, ungranted) = trade(&mut subjects, &mut students, requests.clone());
+
+        assert_eq!(moves.len(), 3);
+        assert_eq!(ungranted, vec![requests[2].clone()]);
+        for (name, division) in &[("1", b), ("2", a), ("3", a), ("4", b), ("5", c), ("6", b)] {
+            assert!(students[students.find(name).unwrap()].is_taking(*division));
+        }
+        for (_, division) in subjects.iter() {
+            assert!(division.assigned <= division.quota);
+        }
+        assert_eq!(subjects[c].assigned, 1);
+    }
+}
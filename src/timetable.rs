@@ -0,0 +1,246 @@
+use std::fs;
+
+use crate::{
+    report::escape_html,
+    student::{Student, Students},
+    subject::{Division, Subjects, Weeks, DAYS},
+};
+
+const PATH: &str = "./timetables";
+
+/// Length of a row in the rendered grid, in minutes.
+const SLOT: i32 = 30;
+const DAY: i32 = 24 * 60;
+/// Days always shown on the grid; weekends are shown only when something meets on them.
+const WEEKDAYS: usize = 5;
+
+/// A student's week, as one column per day and one row per `SLOT` minutes.
+pub struct Timetable<'a> {
+    /// First minute of the day shown on the grid.
+    start: i32,
+    /// Number of days shown, from Monday.
+    days: usize,
+    /// `rows[slot][day]` are the divisions meeting during that slot, several only when they
+    /// meet in different weeks.
+    rows: Vec<Vec<Vec<&'a Division>>>,
+}
+
+impl<'a> Timetable<'a> {
+    pub fn new(divisions: &[&'a Division]) -> Self {
+        let hours = divisions
+            .iter()
+            .flat_map(|&d| d.hour.iter().map(move |h| (d, h)));
+
+        let (mut start, mut end) = (i32::MAX, i32::MIN);
+        for (_, h) in hours.clone() {
+            start = start.min(h[0] % DAY / SLOT * SLOT);
+            end = end.max((h[1] - h[0] / DAY * DAY + SLOT - 1) / SLOT * SLOT);
+        }
+        if start > end {
+            return Timetable {
+                start: 0,
+                days: WEEKDAYS,
+                rows: Vec::new(),
+            };
+        }
+
+        let days = hours
+            .clone()
+            .map(|(_, h)| (h[0] / DAY) as usize + 1)
+            .fold(WEEKDAYS, usize::max);
+        let mut rows = vec![vec![Vec::new(); days]; ((end - start) / SLOT) as usize];
+        for (division, h) in hours {
+            let day = (h[0] / DAY) as usize;
+            for (i, row) in rows.iter_mut().enumerate() {
+                let slot = day as i32 * DAY + start + i as i32 * SLOT;
+                if h[0] < slot + SLOT && slot < h[1] {
+                    row[day].push(division);
+                }
+            }
+        }
+
+        Timetable { start, days, rows }
+    }
+
+    pub fn of(student: &Student, subjects: &'a Subjects) -> Self {
+        let mut divisions = student.subjects(subjects);
+        divisions.sort_by(|a, b| a.id.cmp(&b.id));
+        Self::new(&divisions)
+    }
+
+    fn time(&self, row: usize) -> String {
+        let minute = self.start + row as i32 * SLOT;
+        format!("{:02}:{:02}", minute / 60, minute % 60)
+    }
+
+    pub fn to_text(&self) -> String {
+        let label = |cell: &Vec<&Division>| {
+            cell.iter()
+                .map(|d| format!("{} {}{}", d.id, d.division, period(d)))
+                .collect::<Vec<_>>()
+                .join(" / ")
+        };
+        let width = self
+            .rows
+            .iter()
+            .flatten()
+            .map(|c| columns(&label(c)))
+            .max()
+            .unwrap_or(0)
+            .max(2);
+
+        let mut buf = format!("{:5} |", "");
+        for day in &DAYS[..self.days] {
+            // a Hangul syllable takes two columns on a terminal
+            buf.push_str(&format!(" {}{} |", day, " ".repeat(width - 2)));
+        }
+        buf.push('\n');
+        for (i, row) in self.rows.iter().enumerate() {
+            buf.push_str(&format!("{} |", self.time(i)));
+            for cell in row {
+                let label = label(cell);
+                buf.push_str(&format!(
+                    " {}{} |",
+                    label,
+                    " ".repeat(width - columns(&label))
+                ));
+            }
+            buf.push('\n');
+        }
+        buf
+    }
+
+    pub fn to_html(&self, title: &str) -> String {
+        let mut buf = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+             <style>table {{ border-collapse: collapse; }} td, th {{ border: 1px solid #999; \
+             padding: 2px 6px; font-size: 12px; }} td.class {{ background: #e8f0fe; \
+             text-align: center; }}</style>\n</head>\n<body>\n<h1>{0}</h1>\n<table>\n<tr><th></th>",
+            escape_html(title)
+        );
+        for day in &DAYS[..self.days] {
+            buf.push_str(&format!("<th>{}</th>", day));
+        }
+        buf.push_str("</tr>\n");
+
+        for (i, row) in self.rows.iter().enumerate() {
+            buf.push_str(&format!("<tr><th>{}</th>", self.time(i)));
+            for (day, cell) in row.iter().enumerate() {
+                let same = |r: &Vec<Vec<&Division>>| {
+                    r[day].len() == cell.len()
+                        && r[day].iter().zip(cell).all(|(a, b)| std::ptr::eq(*a, *b))
+                };
+                if cell.is_empty() {
+                    buf.push_str("<td></td>");
+                } else if i == 0 || !same(&self.rows[i - 1]) {
+                    let span = self.rows[i..].iter().take_while(|r| same(r)).count();
+                    let labels: Vec<String> = cell
+                        .iter()
+                        .map(|d| {
+                            format!(
+                                "{} {}{}<br>{}",
+                                escape_html(&d.id),
+                                escape_html(&d.division),
+                                period(d),
+                                escape_html(&d.name),
+                            )
+                        })
+                        .collect();
+                    buf.push_str(&format!(
+                        "<td class=\"class\" rowspan=\"{}\">{}</td>",
+                        span,
+                        labels.join("<hr>")
+                    ));
+                }
+            }
+            buf.push_str("</tr>\n");
+        }
+        buf.push_str("</table>\n</body>\n</html>\n");
+        buf
+    }
+}
+
+/// Width of `s` on a terminal, where a Hangul syllable takes two columns.
+fn columns(s: &str) -> usize {
+    s.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+/// The weeks and dates a division meets in, unless it meets all semester.
+fn period(division: &Division) -> String {
+    let mut parts = Vec::new();
+    if division.weeks != Weeks::Every {
+        parts.push(division.weeks.to_string());
+    }
+    if let Some([first, last]) = division.dates {
+        parts.push(format!(
+            "{}~{}",
+            first.format("%m/%d"),
+            last.format("%m/%d")
+        ));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", parts.join(", "))
+    }
+}
+
+/// Writes `<id>.txt` and `<id>.html` for every student.
+pub fn write_timetables(subjects: &Subjects, students: &Students) {
+    fs::create_dir_all(PATH).unwrap();
+    for student in students.values() {
+        let timetable = Timetable::of(student, subjects);
+        let title = format!("{} {}", student.id, student.name);
+        fs::write(
+            format!("{}/{}.txt", PATH, student.id),
+            format!("{}\n\n{}", title, timetable.to_text()),
+        )
+        .unwrap();
+        fs::write(
+            format!("{}/{}.html", PATH, student.id),
+            timetable.to_html(&title),
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn division(id: &str, division: &str, hour: Vec<[i32; 2]>) -> Division {
+        Division {
+            id: id.to_owned(),
+            division: division.to_owned(),
+            hour,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn text_simple() {
+        let cs101 = division("CS101", "A", vec![[9 * 60, 10 * 60]]);
+        let ch101 = division("CH101", "B", vec![[DAY + 9 * 60 + 30, DAY + 10 * 60]]);
+        let timetable = Timetable::new(&[&cs101, &ch101]);
+        assert_eq!(
+            timetable.to_text(),
+            "      | 월      | 화      | 수      | 목      | 금      |\n\
+             09:00 | CS101 A |         |         |         |         |\n\
+             09:30 | CS101 A | CH101 B |         |         |         |\n"
+        );
+    }
+
+    #[test]
+    fn text_alternating_weeks() {
+        let mut ch102 = division("CH102", "A", vec![[9 * 60, 10 * 60]]);
+        ch102.weeks = Weeks::Odd;
+        let mut ph151 = division("PH151", "B", vec![[9 * 60, 10 * 60]]);
+        ph151.weeks = Weeks::Even;
+        assert!(ch102.is_disjoint(&ph151));
+
+        let timetable = Timetable::new(&[&ch102, &ph151]).to_text();
+        let lines: Vec<&str> = timetable.lines().collect();
+        assert!(lines[1].starts_with("09:00 | CH102 A (홀수주) / PH151 B (짝수주) |"));
+        assert!(lines.iter().all(|l| columns(l) == columns(lines[0])));
+    }
+}
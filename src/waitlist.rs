@@ -0,0 +1,200 @@
+use std::{fs::OpenOptions, path::Path};
+
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::{
+    ids::{DivisionId, StudentId},
+    incremental::Move,
+    input::read_records,
+    passes::{assign, unassign},
+    student::{Status, Students},
+    subject::Subjects,
+};
+
+/// Log of every move made after publication, appended to by each command.
+const PATH_EVENTS: &str = "./events.csv";
+
+#[derive(Debug, Deserialize)]
+struct Row {
+    #[serde(rename = "학번")]
+    student: String,
+    #[serde(rename = "과목번호")]
+    subject: String,
+    #[serde(rename = "분반")]
+    division: String,
+}
+
+/// Reads rows of (`학번`, `과목번호`, `분반`), each student preferring that division. Rows
+/// come in waitlist order; those of students who withdrew are left out.
+pub fn read_preferences(
+    path: &str,
+    subjects: &Subjects,
+    students: &Students,
+) -> Vec<(StudentId, DivisionId)> {
+    let (header, records) = read_records(path, "waitlist", None);
+    records
+        .iter()
+        .filter_map(|record| {
+            let row: Row = record.deserialize(Some(&header)).unwrap();
+            let division = subjects
+                .lookup(&row.subject, &row.division)
+                .unwrap_or_else(|| {
+                    panic!("no division {} {}", row.subject.trim(), row.division.trim())
+                });
+            Some((students.find(&row.student)?, division))
+        })
+        .collect()
+}
+
+/// Puts a student at the end of a division's waitlist, unless they are already on it.
+pub fn enqueue(subjects: &mut Subjects, student: StudentId, division: DivisionId) {
+    let waitlist = &mut subjects[division].waitlist;
+    if !waitlist.contains(&student) {
+        waitlist.push(student);
+    }
+}
+
+/// Offers every free seat to the first student on the division's waitlist whose timetable it
+/// fits, moving them out of their current division of the subject. Students who can no longer
+/// take the seat (withdrawn, already there, pinned elsewhere or not taking the subject) are
+/// dropped from the waitlist; those it does not fit yet stay on it. Repeats until no seat can
+/// be filled, since every promotion may free a seat elsewhere.
+pub fn promote(subjects: &mut Subjects, students: &mut Students) -> Vec<Move> {
+    let mut moves = Vec::new();
+
+    loop {
+        let seats: Vec<DivisionId> = subjects
+            .iter()
+            .filter(|(_, d)| d.assigned < d.quota && !d.waitlist.is_empty())
+            .map(|(division_id, _)| division_id)
+            .collect();
+
+        let mut promoted = false;
+        for division_id in seats {
+            let division = &subjects[division_id];
+            let subject_id = division.subject;
+            let mut waitlist = division.waitlist.clone();
+            waitlist.retain(|&id| match students.get(id) {
+                Some(s) => {
+                    !s.is_taking(division_id)
+                        && !s.pinned.contains(&subject_id)
+                        && s.subjects[subject_id] != Status::No
+                }
+                None => false,
+            });
+            let next = waitlist
+                .iter()
+                .position(|&id| students[id].fits(division_id, subjects));
+
+            if let Some(i) = next {
+                let student = &mut students[waitlist.remove(i)];
+                let from = student.subjects[subject_id].division();
+                unassign(student, subject_id, subjects);
+                assign(student, division_id, subjects);
+                moves.push(Move::new(
+                    student,
+                    subject_id,
+                    from,
+                    Some(division_id),
+                    subjects,
+                ));
+                promoted = true;
+            }
+            subjects[division_id].waitlist = waitlist;
+        }
+
+        if !promoted {
+            return moves;
+        }
+    }
+}
+
+/// Appends `moves` to the event log with the time and `reason`.
+pub fn record(moves: &[Move], reason: &str) {
+    if moves.is_empty() {
+        return;
+    }
+    let exists = Path::new(PATH_EVENTS).exists();
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(PATH_EVENTS)
+        .unwrap();
+    let mut writer = csv::Writer::from_writer(file);
+    if !exists {
+        writer
+            .write_record(["시각", "학번", "과목번호", "이전", "이후", "사유"])
+            .unwrap();
+    }
+
+    let time = Local::now().to_rfc3339();
+    for m in moves {
+        writer
+            .write_record([
+                time.as_str(),
+                &m.student,
+                &m.subject,
+                m.from.as_deref().unwrap_or("-"),
+                m.to.as_deref().unwrap_or("-"),
+                reason,
+            ])
+            .unwrap();
+    }
+    writer.flush().unwrap();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        ids::SubjectId,
+        student::{Statuses, Student},
+        subject::Division,
+    };
+
+    fn student(id: &str, cs101: Status) -> Student {
+        let mut subjects = Statuses::new(Status::No);
+        subjects[SubjectId::of("CS101")] = cs101;
+        Student {
+            id: id.to_owned(),
+            subjects,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn promote_cascades() {
+        let division = |division: &str, assigned, quota| Division {
+            id: "CS101".to_owned(),
+            division: division.to_owned(),
+            assigned,
+            quota,
+            hour: vec![[540, 600]],
+            ..Default::default()
+        };
+        let mut subjects = Subjects::new(vec![division("A", 0, 1), division("B", 1, 1)]);
+        let cs101 = SubjectId::of("CS101");
+        let (a, b) = (subjects.division(cs101, "A"), subjects.division(cs101, "B"));
+
+        let mut students = Students::new(vec![
+            student("1", Status::Enroll(b)),
+            student("2", Status::Undefined),
+            student("3", Status::Undefined),
+        ]);
+        let id = |s| students.find(s).unwrap();
+        let (first, second, withdrawn) = (id("1"), id("2"), id("3"));
+        students.remove(withdrawn);
+
+        enqueue(&mut subjects, withdrawn, a);
+        enqueue(&mut subjects, first, a);
+        enqueue(&mut subjects, second, b);
+        enqueue(&mut subjects, second, b);
+
+        let moves = promote(&mut subjects, &mut students);
+        assert_eq!(moves.len(), 2);
+        assert!(students[first].is_taking(a));
+        assert!(students[second].is_taking(b));
+        assert!(subjects.iter().all(|(_, d)| d.waitlist.is_empty()));
+    }
+}
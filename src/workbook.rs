@@ -0,0 +1,138 @@
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+
+use crate::{
+    ids::SubjectId,
+    output::Schema,
+    report::{self, Table},
+    roster,
+    student::{Student, Students},
+    subject::Subjects,
+};
+
+const PATH: &str = "./output.xlsx";
+
+/// Writes the summary, the assignment and one roster sheet per subject into a single workbook.
+pub fn write_workbook(subjects: &Subjects, students: &Students) {
+    workbook(subjects, students, &Schema::read())
+        .save(PATH)
+        .unwrap();
+}
+
+/// The workbook `write_workbook` writes, with the assignment laid out by `schema`.
+fn workbook(subjects: &Subjects, students: &Students, schema: &Schema) -> Workbook {
+    let mut workbook = Workbook::new();
+
+    write_table(
+        workbook.add_worksheet().set_name("요약").unwrap(),
+        &report::division_stats(subjects, students),
+        true,
+    );
+
+    let mut sorted: Vec<&Student> = students.values().collect();
+    sorted.sort_by(|a, b| a.id.cmp(&b.id));
+    write_table(
+        workbook.add_worksheet().set_name("배정").unwrap(),
+        &schema.table(&sorted, subjects),
+        false,
+    );
+
+    for subject_id in SubjectId::all() {
+        if subjects.of(subject_id).is_empty() {
+            continue;
+        }
+
+        let mut table = Table::default();
+        for &division_id in subjects.of(subject_id) {
+            let roster = roster::roster(subjects, students, division_id);
+            if table.header.is_empty() {
+                table.header.push("분반".to_owned());
+                table.header.extend(roster.header);
+            }
+            for row in roster.rows {
+                let mut r = vec![subjects[division_id].division.clone()];
+                r.extend(row);
+                table.rows.push(r);
+            }
+        }
+        write_table(
+            workbook
+                .add_worksheet()
+                .set_name(subject_id.name())
+                .unwrap(),
+            &table,
+            false,
+        );
+    }
+
+    workbook
+}
+
+/// Writes `table` with a frozen bold header. With `numeric`, cells that parse as numbers are
+/// written as numbers so that they can be summed in Excel.
+fn write_table(sheet: &mut Worksheet, table: &Table, numeric: bool) {
+    let bold = Format::new().set_bold();
+    for (col, cell) in table.header.iter().enumerate() {
+        sheet
+            .write_string_with_format(0, col as u16, cell, &bold)
+            .unwrap();
+    }
+    for (row, cells) in table.rows.iter().enumerate() {
+        for (col, cell) in cells.iter().enumerate() {
+            let (row, col) = (row as u32 + 1, col as u16);
+            match cell.parse::<f64>() {
+                Ok(n) if numeric => sheet.write_number(row, col, n).unwrap(),
+                _ => sheet.write_string(row, col, cell).unwrap(),
+            };
+        }
+    }
+    sheet.set_freeze_panes(1, 0).unwrap();
+    sheet.autofit();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{input::read_records, student::Status, subject::Division};
+    use calamine::{open_workbook_auto, Reader};
+
+    #[test]
+    fn workbook_simple() {
+        let division = |id: &str, division: &str| Division {
+            id: id.to_owned(),
+            division: division.to_owned(),
+            quota: 2,
+            ..Default::default()
+        };
+        let subjects = Subjects::new(vec![
+            division("CS101", "A"),
+            division("CS101", "B"),
+            division("MAS101", "A"),
+        ]);
+        let cs101 = SubjectId::of("CS101");
+        let mut student = Student {
+            id: "20210001".to_owned(),
+            name: "김가야".to_owned(),
+            ..Default::default()
+        };
+        student.subjects[cs101] = Status::Enroll(subjects.division(cs101, "B"));
+        let students = Students::new(vec![student]);
+
+        let path = std::env::temp_dir().join("saenaegi_workbook_simple.xlsx");
+        let path = path.to_str().unwrap();
+        workbook(&subjects, &students, &Schema::default())
+            .save(path)
+            .unwrap();
+
+        let sheets = open_workbook_auto(path).unwrap().sheet_names();
+        assert_eq!(sheets, vec!["요약", "배정", "CS101", "MAS101"]);
+        let (header, records) = read_records(path, "CS101", None);
+        assert_eq!(
+            header.iter().take(2).collect::<Vec<_>>(),
+            vec!["분반", "학번"]
+        );
+        assert_eq!(records.len(), 1);
+        assert_eq!(&records[0][0], "B");
+        assert_eq!(&records[0][1], "20210001");
+        assert_eq!(&records[0][2], "김가야");
+    }
+}